@@ -0,0 +1,110 @@
+//! Compares the boxed tree BVH with the flattened one, run with `cargo bench --bench bvh`.
+
+use raytracing::bvh::{LinearBVH, SplitMethod, BVH};
+use raytracing::hittable::sphere::Sphere;
+use raytracing::hittable::triangle::Triangle;
+use raytracing::hittable::Hittable;
+use raytracing::material::Lambertian;
+use raytracing::ray::Ray;
+use std::time::{Duration, Instant};
+use ultraviolet::Vec3;
+
+/// Small xorshift generator so that every run uses the same scene and rays.
+struct XorShift(u32);
+
+impl XorShift {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f32 / u32::MAX as f32
+    }
+
+    fn vec3(&mut self) -> Vec3 {
+        Vec3::new(self.next(), self.next(), self.next())
+    }
+}
+
+fn spheres(n: usize) -> Vec<Box<dyn Hittable>> {
+    let mut rng = XorShift(1);
+    (0..n)
+        .map(|_| {
+            Box::new(Sphere {
+                center: rng.vec3() * 100.0,
+                radius: 0.1 + rng.next() * 0.3,
+                mat: Lambertian::new(Vec3::one()),
+            }) as Box<dyn Hittable>
+        })
+        .collect()
+}
+
+fn triangles(n: usize) -> Vec<Box<dyn Hittable>> {
+    let mut rng = XorShift(2);
+    (0..n)
+        .map(|_| {
+            let p = rng.vec3() * 100.0;
+            let tri = Triangle::new(
+                [
+                    p,
+                    p + rng.vec3() - Vec3::broadcast(0.5),
+                    p + rng.vec3() - Vec3::broadcast(0.5),
+                ],
+                Lambertian::new(Vec3::one()),
+            );
+            Box::new(tri) as Box<dyn Hittable>
+        })
+        .collect()
+}
+
+fn rays(n: usize) -> Vec<Ray> {
+    let mut rng = XorShift(3);
+    (0..n)
+        .map(|_| {
+            let target = rng.vec3() * 100.0;
+            let pos = Vec3::new(50.0, 50.0, -50.0) + (rng.vec3() - Vec3::broadcast(0.5)) * 20.0;
+            Ray::new(pos, (target - pos).normalized())
+        })
+        .collect()
+}
+
+fn trace(world: &dyn Hittable, rays: &[Ray]) -> (Duration, usize) {
+    let start = Instant::now();
+    let hits = rays
+        .iter()
+        .filter(|r| world.hit(r, 0.001, f32::INFINITY).is_some())
+        .count();
+    (start.elapsed(), hits)
+}
+
+fn bench(name: &str, make: fn(usize) -> Vec<Box<dyn Hittable>>, n: usize, rays: &[Ray]) {
+    println!("{} ({} objects, {} rays)", name, n, rays.len());
+    for &method in &[SplitMethod::Median, SplitMethod::SAH] {
+        let t = Instant::now();
+        let tree = BVH::with_split(make(n), method);
+        let tree_build = t.elapsed();
+
+        let t = Instant::now();
+        let linear = LinearBVH::with_split(make(n), method);
+        let linear_build = t.elapsed();
+
+        let (tree_time, tree_hits) = trace(&tree, rays);
+        let (linear_time, linear_hits) = trace(&linear, rays);
+        assert_eq!(tree_hits, linear_hits);
+
+        let per_ray = |d: Duration| d.as_secs_f64() * 1e9 / rays.len() as f64;
+        println!(
+            "  {:?}\ttree: build {:>7.1}ms, {:>6.0}ns/ray\tlinear: build {:>7.1}ms, {:>6.0}ns/ray",
+            method,
+            tree_build.as_secs_f64() * 1e3,
+            per_ray(tree_time),
+            linear_build.as_secs_f64() * 1e3,
+            per_ray(linear_time),
+        );
+    }
+}
+
+fn main() {
+    let rays = rays(500_000);
+    bench("spheres", spheres, 200_000, &rays);
+    bench("triangles", triangles, 200_000, &rays);
+}
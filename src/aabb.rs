@@ -3,6 +3,7 @@ use crate::utils::{v_max, v_min};
 use ultraviolet::Vec3;
 
 #[derive(Clone, Copy)]
+/// Axis-aligned bounding box.
 pub struct AABB {
     pub lo: Vec3,
     pub hi: Vec3,
@@ -20,7 +21,13 @@ impl AABB {
         }
     }
 
-    pub fn hit(&self, r: &Ray, mut tmin: f32, mut tmax: f32) -> bool {
+    pub fn hit(&self, r: &Ray, tmin: f32, tmax: f32) -> bool {
+        let inv_dir = Vec3::new(1.0 / r.dir.x, 1.0 / r.dir.y, 1.0 / r.dir.z);
+        self.hit_inv(r.pos, inv_dir, tmin, tmax)
+    }
+
+    /// Range of distances along the ray within both the box and `[tmin, tmax]`.
+    pub fn clip(&self, r: &Ray, mut tmin: f32, mut tmax: f32) -> Option<(f32, f32)> {
         for a in 0..3 {
             let inv_d = 1.0 / r.dir[a];
             let mut t0 = (self.lo[a] - r.pos[a]) * inv_d;
@@ -30,6 +37,24 @@ impl AABB {
             }
             tmin = if t0 > tmin { t0 } else { tmin };
             tmax = if t1 < tmax { t1 } else { tmax };
+            if tmax <= tmin {
+                return None;
+            }
+        }
+        Some((tmin, tmax))
+    }
+
+    /// Same as [`hit`](AABB::hit), with the inverse of the ray direction precomputed.
+    pub fn hit_inv(&self, pos: Vec3, inv_dir: Vec3, mut tmin: f32, mut tmax: f32) -> bool {
+        for a in 0..3 {
+            let inv_d = inv_dir[a];
+            let mut t0 = (self.lo[a] - pos[a]) * inv_d;
+            let mut t1 = (self.hi[a] - pos[a]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            tmin = if t0 > tmin { t0 } else { tmin };
+            tmax = if t1 < tmax { t1 } else { tmax };
             if tmax <= tmin {
                 return false;
             }
@@ -37,6 +62,27 @@ impl AABB {
         true
     }
 
+    /// Grows the box so that no side is thinner than `delta`, as flat boxes are never hit.
+    pub fn padded(&self, delta: f32) -> AABB {
+        let mut bbox = *self;
+        for a in 0..3 {
+            if bbox.hi[a] - bbox.lo[a] < delta {
+                bbox.lo[a] -= delta * 0.5;
+                bbox.hi[a] += delta * 0.5;
+            }
+        }
+        bbox
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.lo + self.hi) * 0.5
+    }
+
+    pub fn area(&self) -> f32 {
+        let d = self.hi - self.lo;
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
     pub fn extend(&self, other: &AABB) -> AABB {
         Self {
             lo: v_min(self.lo, other.lo),
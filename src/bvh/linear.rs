@@ -0,0 +1,273 @@
+use super::{BVHNode, BVHNodeKind, SplitMethod, Visits, BVH};
+use crate::aabb::AABB;
+use crate::hittable::{shadow_hit_all, Hit, Hittable};
+use crate::light::Light;
+use crate::ray::Ray;
+use ultraviolet::Vec3;
+
+/// Size of the traversal stack, deeper trees are rebuilt with median splits.
+const MAX_DEPTH: usize = 64;
+
+/// Interior nodes are immediately followed by their first child, leaves point to a range of
+/// objects.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LinearNode {
+    bbox: AABB,
+    /// Index of the second child for interior nodes, of the first object for leaves.
+    offset: u32,
+    /// Number of objects, 0 for interior nodes.
+    count: u16,
+    /// Split axis of interior nodes.
+    axis: u8,
+}
+
+const _: () = assert!(std::mem::size_of::<LinearNode>() == 32);
+
+/// A [`BVH`] flattened into an array of nodes in depth-first order, traversed without recursion.
+pub struct LinearBVH<T: Hittable = Box<dyn Hittable>> {
+    objects: Vec<T>,
+    nodes: Vec<LinearNode>,
+}
+
+fn flatten(node: &BVHNode, nodes: &mut Vec<LinearNode>) {
+    let i = nodes.len();
+    nodes.push(LinearNode {
+        bbox: node.bbox,
+        offset: 0,
+        count: 0,
+        axis: 0,
+    });
+
+    match &node.kind {
+        &BVHNodeKind::Leaf { start, len } => {
+            nodes[i].offset = start as u32;
+            nodes[i].count = len as u16;
+        }
+        BVHNodeKind::Branch { left, right, axis } => {
+            flatten(left, nodes);
+            nodes[i].offset = nodes.len() as u32;
+            nodes[i].axis = *axis as u8;
+            flatten(right, nodes);
+        }
+    }
+}
+
+impl<T: Hittable> LinearBVH<T> {
+    /// Builds the hierarchy using the surface area heuristic.
+    pub fn new(objects: Vec<T>) -> Self {
+        Self::with_split(objects, SplitMethod::SAH)
+    }
+
+    pub fn with_split(objects: Vec<T>, method: SplitMethod) -> Self {
+        let mut bvh = BVH::with_split(objects, method);
+        if bvh.node.as_ref().is_some_and(|x| x.depth() > MAX_DEPTH) {
+            // Median splits are balanced, this only happens with pathological SAH inputs.
+            bvh = BVH::with_split(bvh.objects, SplitMethod::Median);
+        }
+
+        let mut nodes = vec![];
+        if let Some(node) = &bvh.node {
+            flatten(node, &mut nodes);
+        }
+
+        Self {
+            objects: bvh.objects,
+            nodes,
+        }
+    }
+
+    /// Visits the leaves whose bounding box the ray crosses between `t_min` and `t_max`, nearer
+    /// children first. `leaf` is called with the objects of each leaf and `t_max`, which it can
+    /// lower to cull farther nodes. Returns the number of nodes visited.
+    fn traverse<'a>(
+        &'a self,
+        ray: &Ray,
+        t_min: f32,
+        mut t_max: f32,
+        mut leaf: impl FnMut(&'a [T], &mut f32),
+    ) -> u64 {
+        if self.nodes.is_empty() {
+            return 0;
+        }
+
+        let inv_dir = Vec3::new(1.0 / ray.dir.x, 1.0 / ray.dir.y, 1.0 / ray.dir.z);
+        let dir_is_neg = [inv_dir.x < 0.0, inv_dir.y < 0.0, inv_dir.z < 0.0];
+
+        let mut stack = [0u32; MAX_DEPTH];
+        let mut to_visit = 0;
+        let mut current = 0;
+        let mut visited = 0;
+
+        loop {
+            let node = unsafe { self.nodes.get_unchecked(current) };
+            visited += 1;
+            if node.bbox.hit_inv(ray.pos, inv_dir, t_min, t_max) {
+                if node.count > 0 {
+                    let start = node.offset as usize;
+                    let objs = unsafe {
+                        self.objects
+                            .get_unchecked(start..start + node.count as usize)
+                    };
+                    leaf(objs, &mut t_max);
+                } else {
+                    // Visit the nearer child first, so that hits in it cull the other one.
+                    if dir_is_neg[node.axis as usize] {
+                        stack[to_visit] = current as u32 + 1;
+                        current = node.offset as usize;
+                    } else {
+                        stack[to_visit] = node.offset;
+                        current += 1;
+                    }
+                    to_visit += 1;
+                    continue;
+                }
+            }
+
+            if to_visit == 0 {
+                break;
+            }
+            to_visit -= 1;
+            current = stack[to_visit] as usize;
+        }
+
+        visited
+    }
+
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        visits: &mut impl Visits,
+    ) -> Option<Hit<'_>> {
+        let mut closest = None;
+        let visited = self.traverse(ray, t_min, t_max, |objs, t_max| {
+            for obj in objs {
+                if let Some(hit) = visits.hit(obj, ray, t_min, *t_max) {
+                    *t_max = hit.t;
+                    closest = Some(hit);
+                }
+            }
+        });
+        visits.add(visited);
+        closest
+    }
+}
+
+impl<T: Hittable> Hittable for LinearBVH<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        self.closest_hit(ray, t_min, t_max, &mut ())
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f32, t_max: f32, visits: &mut u64) -> Option<Hit<'_>> {
+        self.closest_hit(ray, t_min, t_max, visits)
+    }
+
+    fn bbox(&self) -> Option<AABB> {
+        self.nodes.first().map(|x| x.bbox)
+    }
+
+    fn lights(&self, out: &mut Vec<Box<dyn Light>>) {
+        self.objects.lights(out)
+    }
+
+    fn shadow_hit(&self, ray: &Ray, t_min: f32, t_max: f32, tr: &mut f32) -> Option<Hit<'_>> {
+        let mut closest = None;
+        self.traverse(ray, t_min, t_max, |objs, t_max| {
+            if let Some(hit) = shadow_hit_all(objs, ray, t_min, *t_max, tr) {
+                *t_max = hit.t;
+                closest = Some(hit);
+            }
+        });
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::sphere::Sphere;
+    use crate::hittable::triangle::Triangle;
+    use crate::material::Lambertian;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::sync::Arc;
+
+    fn random_vec(rng: &mut StdRng, lo: f32, hi: f32) -> Vec3 {
+        Vec3::new(
+            rng.gen_range(lo, hi),
+            rng.gen_range(lo, hi),
+            rng.gen_range(lo, hi),
+        )
+    }
+
+    /// Spheres and triangles with a material each, shared between the hierarchies so that a hit
+    /// object can be recognized by the address of its material.
+    fn random_objects(rng: &mut StdRng, n: usize) -> Vec<Arc<dyn Hittable>> {
+        (0..n)
+            .map(|i| -> Arc<dyn Hittable> {
+                let mat = Lambertian::new(Vec3::one());
+                let p = random_vec(rng, -10.0, 10.0);
+                if i % 2 == 0 {
+                    let radius = rng.gen_range(0.1, 1.5);
+                    Arc::new(Sphere {
+                        center: p,
+                        radius,
+                        mat,
+                    })
+                } else {
+                    let vertices = [
+                        p,
+                        p + random_vec(rng, -2.0, 2.0),
+                        p + random_vec(rng, -2.0, 2.0),
+                    ];
+                    Arc::new(Triangle::new(vertices, mat))
+                }
+            })
+            .collect()
+    }
+
+    fn same_hit(a: &Option<Hit<'_>>, b: &Option<Hit<'_>>) -> bool {
+        match (a, b) {
+            (None, None) => true,
+            (Some(a), Some(b)) => a.t == b.t && std::ptr::addr_eq(a.mat, b.mat),
+            _ => false,
+        }
+    }
+
+    fn check(objects: Vec<Arc<dyn Hittable>>, rng: &mut StdRng) {
+        for method in [SplitMethod::Median, SplitMethod::SAH] {
+            let tree = BVH::with_split(objects.clone(), method);
+            let linear = LinearBVH::with_split(objects.clone(), method);
+            for _ in 0..2000 {
+                let dir = random_vec(rng, -1.0, 1.0).normalized();
+                let ray = Ray::new(random_vec(rng, -15.0, 15.0), dir);
+                let t_max = if rng.gen() { f32::INFINITY } else { 10.0 };
+                let expected = objects.hit(&ray, 0.001, t_max);
+                assert!(same_hit(&tree.hit(&ray, 0.001, t_max), &expected));
+                assert!(same_hit(&linear.hit(&ray, 0.001, t_max), &expected));
+            }
+        }
+    }
+
+    #[test]
+    fn same_hits_as_brute_force() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let objects = random_objects(&mut rng, 300);
+        check(objects, &mut rng);
+    }
+
+    #[test]
+    fn empty_and_single_object() {
+        let mut rng = StdRng::seed_from_u64(4);
+        check(vec![], &mut rng);
+        let objects = random_objects(&mut rng, 1);
+        check(objects, &mut rng);
+
+        let empty = LinearBVH::<Arc<dyn Hittable>>::new(vec![]);
+        assert!(empty.bbox().is_none());
+        let ray = Ray::new(Vec3::zero(), Vec3::unit_x());
+        assert!(empty.hit(&ray, 0.0, f32::INFINITY).is_none());
+    }
+}
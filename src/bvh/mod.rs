@@ -0,0 +1,398 @@
+mod linear;
+
+pub use linear::LinearBVH;
+
+use crate::aabb::AABB;
+use crate::hittable::{shadow_hit_all, Hit, Hittable};
+use crate::light::Light;
+use crate::ray::Ray;
+use crate::utils::SampleRng;
+use rand::Rng;
+use rand::SeedableRng;
+use ultraviolet::Vec3;
+
+/// How nodes are split when building a [`BVH`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitMethod {
+    /// Sorts along a random axis and splits in the middle, with one object per leaf. The axes
+    /// are chosen deterministically, so the same objects always give the same tree.
+    Median,
+    /// Binned surface area heuristic: picks the axis and position with the lowest expected
+    /// intersection cost, and stops splitting when it is not worth it.
+    SAH,
+}
+
+/// Counter of the nodes visited while intersecting a ray, `()` when they are not counted so
+/// that [`Hittable::hit`] does not pay for [`Hittable::hit_counted`].
+trait Visits {
+    fn add(&mut self, n: u64);
+    fn hit<'a, T: Hittable>(
+        &mut self,
+        obj: &'a T,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Hit<'a>>;
+}
+
+impl Visits for () {
+    fn add(&mut self, _n: u64) {}
+
+    fn hit<'a, T: Hittable>(
+        &mut self,
+        obj: &'a T,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Hit<'a>> {
+        obj.hit(ray, t_min, t_max)
+    }
+}
+
+impl Visits for u64 {
+    fn add(&mut self, n: u64) {
+        *self += n;
+    }
+
+    fn hit<'a, T: Hittable>(
+        &mut self,
+        obj: &'a T,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+    ) -> Option<Hit<'a>> {
+        obj.hit_counted(ray, t_min, t_max, self)
+    }
+}
+
+const SAH_BINS: usize = 16;
+/// Cost of traversing a node, relative to intersecting an object.
+const SAH_TRAVERSAL_COST: f32 = 0.125;
+const SAH_MAX_LEAF_SIZE: usize = 8;
+
+#[derive(Clone, Copy)]
+struct BuildItem {
+    index: usize,
+    bbox: AABB,
+    centroid: Vec3,
+}
+
+fn items_bbox(items: &[BuildItem]) -> AABB {
+    items
+        .iter()
+        .skip(1)
+        .fold(items[0].bbox, |acc, x| acc.extend(&x.bbox))
+}
+
+fn split_median(items: &mut [BuildItem], offset: usize) -> Option<(usize, usize)> {
+    // Seeded by the position of the node so that building in parallel stays deterministic.
+    let mut rng = SampleRng::seed_from_u64(((items.len() as u64) << 32) ^ offset as u64);
+    let axis = rng.gen_range(0, 3);
+    items.sort_by(move |a, b| a.bbox.lo[axis].partial_cmp(&b.bbox.lo[axis]).unwrap());
+    Some((items.len() / 2, axis))
+}
+
+/// Returns where to split the (reordered) items and along which axis, or `None` if they should
+/// stay in one leaf.
+fn split_sah(items: &mut [BuildItem]) -> Option<(usize, usize)> {
+    let n = items.len();
+    let bbox = items_bbox(items);
+    let centroids = items
+        .iter()
+        .skip(1)
+        .fold(AABB::new(items[0].centroid, items[0].centroid), |acc, x| {
+            acc.extend(&AABB::new(x.centroid, x.centroid))
+        });
+
+    let area = bbox.area();
+    let inv_area = if area > 0.0 { 1.0 / area } else { 0.0 };
+
+    let bin_of = |c: Vec3, axis: usize| {
+        let extent = centroids.hi[axis] - centroids.lo[axis];
+        let b = ((c[axis] - centroids.lo[axis]) / extent * SAH_BINS as f32) as usize;
+        b.min(SAH_BINS - 1)
+    };
+
+    // (cost, axis, number of bins on the left)
+    let mut best: Option<(f32, usize, usize)> = None;
+
+    for axis in 0..3 {
+        if centroids.hi[axis] - centroids.lo[axis] <= 0.0 {
+            continue;
+        }
+
+        let mut counts = [0usize; SAH_BINS];
+        let mut bounds: [Option<AABB>; SAH_BINS] = [None; SAH_BINS];
+        for item in items.iter() {
+            let b = bin_of(item.centroid, axis);
+            counts[b] += 1;
+            bounds[b] = Some(bounds[b].map_or(item.bbox, |x| x.extend(&item.bbox)));
+        }
+
+        // Sweep from the right to get the area and count of every right side.
+        let mut right_cost = [0.0; SAH_BINS];
+        let mut acc: Option<AABB> = None;
+        let mut count = 0;
+        for b in (1..SAH_BINS).rev() {
+            if let Some(x) = bounds[b] {
+                acc = Some(acc.map_or(x, |a| a.extend(&x)));
+            }
+            count += counts[b];
+            right_cost[b] = acc.map_or(0.0, |a| a.area()) * count as f32;
+        }
+
+        let mut acc: Option<AABB> = None;
+        let mut count = 0;
+        for b in 0..SAH_BINS - 1 {
+            if let Some(x) = bounds[b] {
+                acc = Some(acc.map_or(x, |a| a.extend(&x)));
+            }
+            count += counts[b];
+            if count == 0 || count == n {
+                continue;
+            }
+            let left_cost = acc.map_or(0.0, |a| a.area()) * count as f32;
+            let cost = SAH_TRAVERSAL_COST + (left_cost + right_cost[b + 1]) * inv_area;
+            if best.is_none_or(|(c, _, _)| cost < c) {
+                best = Some((cost, axis, b + 1));
+            }
+        }
+    }
+
+    let (cost, axis, split) = match best {
+        Some(x) => x,
+        // All centroids are at the same place, only split to keep leaves small.
+        None if n > SAH_MAX_LEAF_SIZE => return Some((n / 2, 0)),
+        None => return None,
+    };
+
+    if n <= SAH_MAX_LEAF_SIZE && cost >= n as f32 {
+        return None;
+    }
+
+    let mut mid = 0;
+    for i in 0..n {
+        if bin_of(items[i].centroid, axis) < split {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    Some((mid, axis))
+}
+
+enum BVHNodeKind {
+    Leaf {
+        start: usize,
+        len: usize,
+    },
+    Branch {
+        left: Box<BVHNode>,
+        right: Box<BVHNode>,
+        axis: usize,
+    },
+}
+
+struct BVHNode {
+    kind: BVHNodeKind,
+    bbox: AABB,
+}
+
+impl BVHNode {
+    fn new(items: &mut [BuildItem], offset: usize, method: SplitMethod) -> Self {
+        let split = match (items.len(), method) {
+            (0, _) => panic!("empty node"),
+            (1, _) => None,
+            (_, SplitMethod::Median) => split_median(items, offset),
+            (_, SplitMethod::SAH) => split_sah(items),
+        };
+
+        let (mid, axis) = match split {
+            Some(x) => x,
+            None => {
+                return Self {
+                    kind: BVHNodeKind::Leaf {
+                        start: offset,
+                        len: items.len(),
+                    },
+                    bbox: items_bbox(items),
+                }
+            }
+        };
+
+        let l = items.len();
+        let (left, right) = items.split_at_mut(mid);
+
+        let off_right = offset + left.len();
+
+        let (left, right) = if l > 50 {
+            rayon::join(
+                || Self::new(left, offset, method),
+                || Self::new(right, off_right, method),
+            )
+        } else {
+            (
+                Self::new(left, offset, method),
+                Self::new(right, off_right, method),
+            )
+        };
+
+        let bbox = left.bbox.extend(&right.bbox);
+        Self {
+            kind: BVHNodeKind::Branch {
+                left: Box::new(left),
+                right: Box::new(right),
+                axis,
+            },
+            bbox,
+        }
+    }
+
+    fn depth(&self) -> usize {
+        match &self.kind {
+            BVHNodeKind::Leaf { .. } => 1,
+            BVHNodeKind::Branch { left, right, .. } => 1 + left.depth().max(right.depth()),
+        }
+    }
+
+    fn hit<'a, T: Hittable>(
+        &'a self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        objs: &'a [T],
+        visits: &mut impl Visits,
+    ) -> Option<Hit<'a>> {
+        visits.add(1);
+        if !self.bbox.hit(ray, t_min, t_max) || t_max <= t_min {
+            return None;
+        }
+
+        match &self.kind {
+            &BVHNodeKind::Leaf { start, len } => {
+                let mut closest = None;
+                let mut t_max = t_max;
+                for obj in unsafe { objs.get_unchecked(start..start + len) } {
+                    if let Some(hit) = visits.hit(obj, ray, t_min, t_max) {
+                        t_max = hit.t;
+                        closest = Some(hit);
+                    }
+                }
+                closest
+            }
+            BVHNodeKind::Branch { left, right, .. } => {
+                let hit1 = left.hit(ray, t_min, t_max, objs, visits);
+                let t_max = hit1.as_ref().map_or(t_max, |h| h.t);
+                let hit2 = right.hit(ray, t_min, t_max, objs, visits);
+
+                hit2.or(hit1)
+            }
+        }
+    }
+
+    fn shadow_hit<'a, T: Hittable>(
+        &'a self,
+        ray: &Ray,
+        t_min: f32,
+        t_max: f32,
+        objs: &'a [T],
+        tr: &mut f32,
+    ) -> Option<Hit<'a>> {
+        if !self.bbox.hit(ray, t_min, t_max) || t_max <= t_min {
+            return None;
+        }
+
+        match &self.kind {
+            &BVHNodeKind::Leaf { start, len } => {
+                let objs = unsafe { objs.get_unchecked(start..start + len) };
+                shadow_hit_all(objs, ray, t_min, t_max, tr)
+            }
+            BVHNodeKind::Branch { left, right, .. } => {
+                let hit1 = left.shadow_hit(ray, t_min, t_max, objs, tr);
+                let t_max = hit1.as_ref().map_or(t_max, |h| h.t);
+                let hit2 = right.shadow_hit(ray, t_min, t_max, objs, tr);
+
+                hit2.or(hit1)
+            }
+        }
+    }
+}
+
+/// Bounding volume hierarchy over a list of objects, to intersect them in logarithmic time.
+pub struct BVH<T: Hittable = Box<dyn Hittable>> {
+    objects: Vec<T>,
+    node: Option<BVHNode>,
+}
+
+impl<T: Hittable> BVH<T> {
+    /// Builds the hierarchy using the surface area heuristic.
+    pub fn new(objects: Vec<T>) -> Self {
+        Self::with_split(objects, SplitMethod::SAH)
+    }
+
+    pub fn with_split(objects: Vec<T>, method: SplitMethod) -> Self {
+        if objects.is_empty() {
+            return Self {
+                objects,
+                node: None,
+            };
+        }
+
+        let mut items: Vec<BuildItem> = objects
+            .iter()
+            .enumerate()
+            .map(|(index, obj)| {
+                let bbox = obj.bbox().unwrap();
+                BuildItem {
+                    index,
+                    bbox,
+                    centroid: bbox.center(),
+                }
+            })
+            .collect();
+
+        let node = BVHNode::new(&mut items, 0, method);
+
+        // Leaves refer to ranges of the items, store the objects in the same order.
+        let mut slots: Vec<Option<T>> = objects.into_iter().map(Some).collect();
+        let objects = items
+            .iter()
+            .map(|item| slots[item.index].take().unwrap())
+            .collect();
+
+        Self {
+            objects,
+            node: Some(node),
+        }
+    }
+}
+
+impl<T: Hittable> Hittable for BVH<T> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        match &self.node {
+            Some(x) => x.hit(ray, t_min, t_max, &self.objects, &mut ()),
+            None => None,
+        }
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f32, t_max: f32, visits: &mut u64) -> Option<Hit<'_>> {
+        match &self.node {
+            Some(x) => x.hit(ray, t_min, t_max, &self.objects, visits),
+            None => None,
+        }
+    }
+
+    fn bbox(&self) -> Option<AABB> {
+        self.node.as_ref().map(|x| x.bbox)
+    }
+
+    fn lights(&self, out: &mut Vec<Box<dyn Light>>) {
+        self.objects.lights(out)
+    }
+
+    fn shadow_hit(&self, ray: &Ray, t_min: f32, t_max: f32, tr: &mut f32) -> Option<Hit<'_>> {
+        match &self.node {
+            Some(x) => x.shadow_hit(ray, t_min, t_max, &self.objects, tr),
+            None => None,
+        }
+    }
+}
@@ -1,13 +1,27 @@
 use crate::ray::Ray;
 use crate::raytrace::vec3;
+use crate::utils::{random_in_disk, random_in_polygon, SampleRng};
+use rand::Rng;
 use ultraviolet::Vec3;
 
+/// A thin-lens camera at `pos`, looking in the direction of `-eye`. With a zero `aperture` it
+/// is a pinhole camera and everything is in focus.
 pub struct Camera {
     pub pos: Vec3,
     pub eye: Vec3,
     pub up: Vec3,
+    /// Vertical field of view in degrees.
     pub fov: f32,
     pub aspect_ratio: f32,
+    /// Diameter of the lens.
+    pub aperture: f32,
+    /// Distance from `pos` to the plane in focus, along the view direction.
+    pub focus_dist: f32,
+    /// Number of diaphragm blades, giving polygonal bokeh, or 0 for a round lens.
+    pub blades: u32,
+    /// Rays are traced at random times between the opening and closing of the shutter.
+    pub shutter_open: f32,
+    pub shutter_close: f32,
 }
 
 impl Default for Camera {
@@ -18,15 +32,26 @@ impl Default for Camera {
             up: Vec3::unit_y(),
             fov: 80.0,
             aspect_ratio: 16.0 / 9.0,
+            aperture: 0.0,
+            focus_dist: 10.0,
+            blades: 0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 }
 
+/// Precomputed viewport of a [`Camera`], used to generate primary rays.
 pub struct RayGenerator {
     pos: Vec3,
     ll: Vec3,
     horiz: Vec3,
     vert: Vec3,
+    u: Vec3,
+    v: Vec3,
+    lens_radius: f32,
+    blades: u32,
+    shutter: (f32, f32),
 }
 
 impl Camera {
@@ -40,15 +65,22 @@ impl Camera {
         let u = self.up.cross(self.eye).normalized();
         let v = self.eye.cross(u);
 
-        let horizontal = viewport_width * u;
-        let vertical = viewport_height * v;
-        let lower_left_corner = -horizontal / 2.0 - vertical / 2.0 - self.eye;
+        // The viewport lies on the focus plane, so that rays from any point of the lens
+        // converge there.
+        let horizontal = self.focus_dist * viewport_width * u;
+        let vertical = self.focus_dist * viewport_height * v;
+        let lower_left_corner = -horizontal / 2.0 - vertical / 2.0 - self.focus_dist * self.eye;
 
         RayGenerator {
             pos: self.pos,
             ll: lower_left_corner,
             horiz: horizontal,
             vert: vertical,
+            u,
+            v,
+            lens_radius: self.aperture / 2.0,
+            blades: self.blades,
+            shutter: (self.shutter_open, self.shutter_close),
         }
     }
 
@@ -88,10 +120,42 @@ impl Camera {
 }
 
 impl RayGenerator {
-    pub fn ray(&self, u: f32, v: f32) -> Ray {
-        Ray::new(
+    /// Ray going through the point `(u, v)` of the viewport, in `[0, 1]` from the lower left
+    /// corner, starting from a random point of the lens at a random time.
+    pub fn ray(&self, u: f32, v: f32, rng: &mut SampleRng) -> Ray {
+        let (open, close) = self.shutter;
+        let time = if close > open {
+            open + rng.gen::<f32>() * (close - open)
+        } else {
+            open
+        };
+
+        if self.lens_radius <= 0.0 {
+            let mut ray = self.center_ray(u, v);
+            ray.time = time;
+            return ray;
+        }
+
+        let (x, y) = if self.blades >= 3 {
+            random_in_polygon(rng, self.blades)
+        } else {
+            random_in_disk(rng)
+        };
+        let offset = self.lens_radius * (self.u * x + self.v * y);
+        Ray::with_time(
+            self.pos + offset,
+            (self.ll + self.horiz * u + self.vert * v - offset).normalized(),
+            time,
+        )
+    }
+
+    /// Same as [`ray`](RayGenerator::ray), through the center of the lens when the shutter
+    /// opens.
+    pub fn center_ray(&self, u: f32, v: f32) -> Ray {
+        Ray::with_time(
             self.pos,
             (self.ll + self.horiz * u + self.vert * v).normalized(),
+            self.shutter.0,
         )
     }
 }
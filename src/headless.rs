@@ -0,0 +1,119 @@
+use raytracing::hittable::Hittable;
+use raytracing::image_gen::ImageProvider;
+use raytracing::output;
+use raytracing::raytrace::RayTracer;
+use raytracing::tonemap::ToneMapper;
+use std::time::{Duration, Instant};
+
+pub enum Budget {
+    Samples(u32),
+    Time(Duration),
+}
+
+pub struct HeadlessOptions {
+    pub width: usize,
+    pub height: usize,
+    pub budget: Budget,
+    pub output: String,
+    pub tone_mapper: ToneMapper,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            budget: Budget::Samples(64),
+            output: "render.png".to_owned(),
+            tone_mapper: ToneMapper::default(),
+        }
+    }
+}
+
+pub const USAGE: &str =
+    "usage: raytracing --headless [--scene FILE] [--seed N] [--depth N | DIFFUSE,SPECULAR,TRANSMISSION] \
+                         [--integrator NAME] \
+                         [--width W] [--height H] [--samples N | --time SECONDS] [--output FILE] \
+                         [--tonemap linear|reinhard|reinhard-extended|aces|hable] [--exposure EV]";
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<&String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("missing value for {}", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
+
+impl HeadlessOptions {
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut opts = Self::default();
+        let mut it = args.iter();
+        while let Some(arg) = it.next() {
+            match arg.as_str() {
+                "--headless" => {}
+                "--width" => opts.width = parse_value(arg, it.next())?,
+                "--height" => opts.height = parse_value(arg, it.next())?,
+                "--samples" => opts.budget = Budget::Samples(parse_value(arg, it.next())?),
+                "--time" => {
+                    let value = it.next();
+                    let secs: f32 = parse_value(arg, value)?;
+                    let time = Duration::try_from_secs_f32(secs.max(0.0))
+                        .map_err(|_| format!("invalid value for {}: {}", arg, value.unwrap()))?;
+                    opts.budget = Budget::Time(time);
+                }
+                "--output" | "-o" => {
+                    opts.output = parse_value(arg, it.next())?;
+                    if output::ImageFormat::from_path(opts.output.as_ref()).is_none() {
+                        return Err(format!(
+                            "unknown image format for {}, expected .png, .ppm or .pfm",
+                            opts.output
+                        ));
+                    }
+                }
+                "--tonemap" => {
+                    let name: String = parse_value(arg, it.next())?;
+                    opts.tone_mapper.operator = name.parse()?;
+                }
+                "--exposure" => opts.tone_mapper.exposure = parse_value(arg, it.next())?,
+                x => return Err(format!("unknown argument: {}", x)),
+            }
+        }
+
+        if opts.width == 0 || opts.height == 0 {
+            return Err("resolution must be at least 1x1".to_owned());
+        }
+        if let Budget::Samples(0) = opts.budget {
+            return Err("--samples must be at least 1".to_owned());
+        }
+
+        Ok(opts)
+    }
+}
+
+pub fn render<T: Hittable + Sync>(
+    tracer: &mut RayTracer<T>,
+    opts: &HeadlessOptions,
+) -> std::io::Result<()> {
+    let mut provider = ImageProvider::new();
+    provider.tone_mapper = opts.tone_mapper;
+    let start = Instant::now();
+
+    loop {
+        provider.get_next(tracer, opts.width, opts.height);
+
+        let done = match opts.budget {
+            Budget::Samples(n) => provider.samples() >= n,
+            Budget::Time(d) => start.elapsed() >= d,
+        };
+        if done {
+            break;
+        }
+    }
+
+    println!(
+        "Rendered {} samples in {:.2}s",
+        provider.samples(),
+        start.elapsed().as_secs_f32()
+    );
+
+    output::save(&provider, &opts.output)
+}
@@ -0,0 +1,171 @@
+use crate::aabb::AABB;
+use crate::hittable::{Hit, Hittable};
+use crate::material::{DiffuseLight, Isotropic, Material};
+use crate::ray::Ray;
+use crate::raytrace::Color;
+use crate::utils::ray_rng;
+use crate::volume::DensityGrid;
+use rand::Rng;
+use std::sync::Arc;
+
+/// Volume of uniform `density` filling a `boundary`, such as fog or smoke. Rays cross it for an
+/// exponentially distributed distance before hitting a particle, which scatters them according
+/// to the `phase` material, usually [`Isotropic`].
+///
+/// The boundary must be convex, a ray entering it only once. Its own material is ignored.
+pub struct ConstantMedium<H: Hittable, M: Material = Isotropic> {
+    pub boundary: H,
+    pub phase: M,
+    neg_inv_density: f32,
+}
+
+impl<H: Hittable, M: Material> ConstantMedium<H, M> {
+    pub fn new(boundary: H, density: f32, phase: M) -> Self {
+        Self {
+            boundary,
+            phase,
+            neg_inv_density: -1.0 / density,
+        }
+    }
+
+    pub fn density(&self) -> f32 {
+        -1.0 / self.neg_inv_density
+    }
+}
+
+/// Part of the ray inside `boundary` within `[t_min, t_max]`, if any. The ray may start inside.
+pub(crate) fn inside<H: Hittable>(
+    boundary: &H,
+    r: &Ray,
+    t_min: f32,
+    t_max: f32,
+) -> Option<(f32, f32)> {
+    let entry = boundary.hit(r, f32::NEG_INFINITY, f32::INFINITY)?.t;
+    let exit = boundary.hit(r, entry + 1e-4, f32::INFINITY)?.t;
+    let (t0, t1) = (entry.max(t_min), exit.min(t_max));
+    if t0 < t1 {
+        Some((t0, t1))
+    } else {
+        None
+    }
+}
+
+/// Hit at a particle of a medium, which has no surface: the normal faces the ray.
+pub(crate) fn scattering_hit<'a>(r: &Ray, t: f32, phase: &'a dyn Material) -> Hit<'a> {
+    Hit::new(r, r.at(t), -r.dir, t, phase)
+}
+
+impl<H: Hittable, M: Material> Hittable for ConstantMedium<H, M> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        let (t0, t1) = inside(&self.boundary, r, t_min, t_max)?;
+
+        let dist = self.neg_inv_density * ray_rng(r).gen::<f32>().ln();
+        if dist > t1 - t0 {
+            return None;
+        }
+        Some(scattering_hit(r, t0 + dist, &self.phase))
+    }
+
+    fn bbox(&self) -> Option<AABB> {
+        self.boundary.bbox()
+    }
+}
+
+/// Volume whose density varies, given by a [`DensityGrid`], such as a cloud or an explosion.
+/// Where the density is `d`, particles absorb light with the coefficient `absorption * d`,
+/// glowing with the radiance `emission` as they do, and scatter it with `scattering * d`
+/// according to the `phase` material.
+///
+/// Collisions are found by delta tracking: tentative collisions are sampled as in a medium
+/// of the maximum density, the majorant, and those exceeding the actual density are ignored.
+/// Shadow rays go through the medium and are dimmed by its transmittance instead.
+pub struct GridMedium<M: Material = Isotropic> {
+    pub grid: Arc<DensityGrid>,
+    pub absorption: f32,
+    pub scattering: f32,
+    pub phase: M,
+    absorber: DiffuseLight,
+}
+
+impl<M: Material> GridMedium<M> {
+    pub fn new(
+        grid: Arc<DensityGrid>,
+        absorption: f32,
+        scattering: f32,
+        emission: Color,
+        phase: M,
+    ) -> Self {
+        Self {
+            grid,
+            absorption,
+            scattering,
+            phase,
+            absorber: DiffuseLight::new(emission),
+        }
+    }
+
+    pub fn emission(&self) -> Color {
+        self.absorber.emit
+    }
+
+    fn majorant(&self) -> f32 {
+        self.grid.max_density() * (self.absorption + self.scattering)
+    }
+
+    /// Fraction of the light going through the medium along `r` between `t_min` and `t_max`,
+    /// estimated by ratio tracking: every tentative collision attenuates it by the probability
+    /// of it being real, which has much less variance than counting real collisions.
+    fn transmittance(&self, r: &Ray, t_min: f32, t_max: f32) -> f32 {
+        let majorant = self.majorant();
+        let (mut t, t1) = match self.grid.bbox().clip(r, t_min, t_max) {
+            Some(x) if majorant > 0.0 => x,
+            _ => return 1.0,
+        };
+
+        let extinction = self.absorption + self.scattering;
+        let mut rng = ray_rng(r);
+        let mut tr = 1.0;
+        loop {
+            t -= (1.0 - rng.gen::<f32>()).ln() / majorant;
+            if t >= t1 {
+                return tr;
+            }
+            tr *= 1.0 - extinction * self.grid.density(r.at(t)) / majorant;
+        }
+    }
+}
+
+impl<M: Material> Hittable for GridMedium<M> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return None;
+        }
+        let (mut t, t1) = self.grid.bbox().clip(r, t_min, t_max)?;
+
+        let mut rng = ray_rng(r);
+        loop {
+            t -= (1.0 - rng.gen::<f32>()).ln() / majorant;
+            if t >= t1 {
+                return None;
+            }
+            let density = self.grid.density(r.at(t));
+            let x = rng.gen::<f32>() * majorant;
+            if x < self.absorption * density {
+                return Some(scattering_hit(r, t, &self.absorber));
+            }
+            if x < (self.absorption + self.scattering) * density {
+                return Some(scattering_hit(r, t, &self.phase));
+            }
+        }
+    }
+
+    fn bbox(&self) -> Option<AABB> {
+        Some(self.grid.bbox())
+    }
+
+    fn shadow_hit(&self, r: &Ray, t_min: f32, t_max: f32, tr: &mut f32) -> Option<Hit<'_>> {
+        *tr *= self.transmittance(r, t_min, t_max);
+        None
+    }
+}
@@ -0,0 +1,168 @@
+use crate::aabb::AABB;
+use crate::bvh::LinearBVH;
+use crate::hittable::triangle::{bbox, intersect, make_hit};
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use std::sync::Arc;
+use ultraviolet::{Vec2, Vec3};
+
+struct MeshData<T: Material> {
+    positions: Vec<Vec3>,
+    normals: Option<Vec<Vec3>>,
+    uvs: Option<Vec<Vec2>>,
+    mat: T,
+}
+
+impl<T: Material> MeshData<T> {
+    fn positions(&self, idx: [u32; 3]) -> [Vec3; 3] {
+        [
+            self.positions[idx[0] as usize],
+            self.positions[idx[1] as usize],
+            self.positions[idx[2] as usize],
+        ]
+    }
+
+    fn normals(&self, idx: [u32; 3]) -> Option<[Vec3; 3]> {
+        self.normals
+            .as_ref()
+            .map(|n| [n[idx[0] as usize], n[idx[1] as usize], n[idx[2] as usize]])
+    }
+
+    fn uvs(&self, idx: [u32; 3]) -> Option<[Vec2; 3]> {
+        self.uvs.as_ref().map(|uv| {
+            [
+                uv[idx[0] as usize],
+                uv[idx[1] as usize],
+                uv[idx[2] as usize],
+            ]
+        })
+    }
+}
+
+struct MeshTriangle<T: Material> {
+    mesh: Arc<MeshData<T>>,
+    idx: [u32; 3],
+}
+
+impl<T: Material> Hittable for MeshTriangle<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        let p = self.mesh.positions(self.idx);
+        let (t, bary) = intersect(r, p, t_min, t_max)?;
+        Some(make_hit(
+            r,
+            t,
+            bary,
+            p,
+            self.mesh.normals(self.idx),
+            self.mesh.uvs(self.idx),
+            &self.mesh.mat,
+        ))
+    }
+
+    fn bbox(&self) -> Option<AABB> {
+        Some(bbox(self.mesh.positions(self.idx)))
+    }
+}
+
+/// Indexed triangle mesh sharing one material, with its own [`LinearBVH`] over the triangles.
+pub struct TriangleMesh<T: Material> {
+    bvh: LinearBVH<MeshTriangle<T>>,
+    n_triangles: usize,
+}
+
+impl<T: Material> TriangleMesh<T> {
+    /// Each triangle is three indices into `positions`. If given, `normals` and texture
+    /// coordinates `uvs` are per vertex and indexed the same way, they are interpolated across
+    /// the triangles.
+    ///
+    /// Panics if an index is out of bounds or if `normals` or `uvs` and `positions` differ in
+    /// length.
+    pub fn new(
+        positions: Vec<Vec3>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<Vec2>>,
+        indices: Vec<[u32; 3]>,
+        mat: T,
+    ) -> Self {
+        if let Some(n) = &normals {
+            assert_eq!(n.len(), positions.len(), "one normal per vertex expected");
+        }
+        if let Some(uv) = &uvs {
+            assert_eq!(uv.len(), positions.len(), "one uv per vertex expected");
+        }
+        assert!(
+            indices
+                .iter()
+                .flatten()
+                .all(|&i| (i as usize) < positions.len()),
+            "vertex index out of bounds"
+        );
+
+        let n_triangles = indices.len();
+        let mesh = Arc::new(MeshData {
+            positions,
+            normals,
+            uvs,
+            mat,
+        });
+
+        let triangles = indices
+            .into_iter()
+            .map(|idx| MeshTriangle {
+                mesh: mesh.clone(),
+                idx,
+            })
+            .collect();
+
+        Self {
+            bvh: LinearBVH::new(triangles),
+            n_triangles,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.n_triangles
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n_triangles == 0
+    }
+}
+
+impl<T: Material> Hittable for TriangleMesh<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        self.bvh.hit(r, t_min, t_max)
+    }
+
+    fn bbox(&self) -> Option<AABB> {
+        self.bvh.bbox()
+    }
+
+    fn hit_counted(&self, r: &Ray, t_min: f32, t_max: f32, visits: &mut u64) -> Option<Hit<'_>> {
+        self.bvh.hit_counted(r, t_min, t_max, visits)
+    }
+}
+
+/// Area-weighted vertex normals, to smooth meshes that come without normals.
+pub fn smooth_normals(positions: &[Vec3], indices: &[[u32; 3]]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::zero(); positions.len()];
+    for idx in indices {
+        let [a, b, c] = [
+            positions[idx[0] as usize],
+            positions[idx[1] as usize],
+            positions[idx[2] as usize],
+        ];
+        // Not normalized, so that larger triangles weigh more.
+        let n = (b - a).cross(c - a);
+        for &i in idx {
+            normals[i as usize] += n;
+        }
+    }
+    for n in &mut normals {
+        if n.mag_sq() > 0.0 {
+            n.normalize();
+        }
+    }
+    normals
+}
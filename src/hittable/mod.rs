@@ -1,20 +1,47 @@
+pub mod medium;
+pub mod mesh;
+pub mod quad;
 pub mod sphere;
+pub mod transform;
+pub mod triangle;
 
 use crate::aabb::AABB;
+use crate::light::Light;
 use crate::material::Material;
 use crate::ray::Ray;
 use ordered_float::OrderedFloat;
 use std::ops::Deref;
 use std::sync::Arc;
-use ultraviolet::Vec3;
+use ultraviolet::{Vec2, Vec3};
 
+/// Anything a ray can intersect.
 pub trait Hittable: Send + Sync {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit>;
+    /// Returns the closest intersection with `ray` whose distance lies in `[t_min, t_max]`.
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>>;
+    /// Bounding box of the object at every instant, `None` if it is unbounded.
     fn bbox(&self) -> Option<AABB>;
+    /// Adds the light sources of the object that can be sampled directly, see [`Light`]. Other
+    /// emitting objects are only found when rays hit them by chance, which includes moving
+    /// spheres and anything inside a [`Transformed`](transform::Transformed), as lights are
+    /// sampled in world space.
+    fn lights(&self, _out: &mut Vec<Box<dyn Light>>) {}
+
+    /// Like [`hit`](Hittable::hit), for shadow rays: participating media that can estimate the
+    /// fraction of the light getting through them multiply it into `tr` instead of being hit,
+    /// which has less variance than sampling a collision.
+    fn shadow_hit(&self, ray: &Ray, t_min: f32, t_max: f32, _tr: &mut f32) -> Option<Hit<'_>> {
+        self.hit(ray, t_min, t_max)
+    }
+
+    /// Like [`hit`](Hittable::hit), also adding the number of bounding volume hierarchy nodes
+    /// visited to `visits`, to show the cost of tracing rays.
+    fn hit_counted(&self, ray: &Ray, t_min: f32, t_max: f32, _visits: &mut u64) -> Option<Hit<'_>> {
+        self.hit(ray, t_min, t_max)
+    }
 }
 
 impl Hittable for () {
-    fn hit(&self, _ray: &Ray, _t_min: f32, _t_max: f32) -> Option<Hit> {
+    fn hit(&self, _ray: &Ray, _t_min: f32, _t_max: f32) -> Option<Hit<'_>> {
         None
     }
 
@@ -24,27 +51,51 @@ impl Hittable for () {
 }
 
 impl Hittable for Box<dyn Hittable> {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
         self.deref().hit(ray, t_min, t_max)
     }
 
     fn bbox(&self) -> Option<AABB> {
         self.deref().bbox()
     }
+
+    fn lights(&self, out: &mut Vec<Box<dyn Light>>) {
+        self.deref().lights(out)
+    }
+
+    fn shadow_hit(&self, ray: &Ray, t_min: f32, t_max: f32, tr: &mut f32) -> Option<Hit<'_>> {
+        self.deref().shadow_hit(ray, t_min, t_max, tr)
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f32, t_max: f32, visits: &mut u64) -> Option<Hit<'_>> {
+        self.deref().hit_counted(ray, t_min, t_max, visits)
+    }
 }
 
 impl Hittable for Arc<dyn Hittable> {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
         self.deref().hit(ray, t_min, t_max)
     }
 
     fn bbox(&self) -> Option<AABB> {
         self.deref().bbox()
     }
+
+    fn lights(&self, out: &mut Vec<Box<dyn Light>>) {
+        self.deref().lights(out)
+    }
+
+    fn shadow_hit(&self, ray: &Ray, t_min: f32, t_max: f32, tr: &mut f32) -> Option<Hit<'_>> {
+        self.deref().shadow_hit(ray, t_min, t_max, tr)
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f32, t_max: f32, visits: &mut u64) -> Option<Hit<'_>> {
+        self.deref().hit_counted(ray, t_min, t_max, visits)
+    }
 }
 
 impl<T: Hittable> Hittable for &[T] {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
         self.iter()
             .filter_map(move |x| x.hit(ray, t_min, t_max))
             .min_by_key(|rec| OrderedFloat(rec.t))
@@ -58,10 +109,24 @@ impl<T: Hittable> Hittable for &[T] {
         }
         Some(bbox)
     }
+
+    fn lights(&self, out: &mut Vec<Box<dyn Light>>) {
+        for obj in self.iter() {
+            obj.lights(out);
+        }
+    }
+
+    fn shadow_hit(&self, ray: &Ray, t_min: f32, t_max: f32, tr: &mut f32) -> Option<Hit<'_>> {
+        shadow_hit_all(self, ray, t_min, t_max, tr)
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f32, t_max: f32, visits: &mut u64) -> Option<Hit<'_>> {
+        hit_counted_all(self, ray, t_min, t_max, visits)
+    }
 }
 
 impl<T: Hittable> Hittable for Vec<T> {
-    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
         self.iter()
             .filter_map(move |x| x.hit(ray, t_min, t_max))
             .min_by_key(|rec| OrderedFloat(rec.t))
@@ -70,13 +135,72 @@ impl<T: Hittable> Hittable for Vec<T> {
     fn bbox(&self) -> Option<AABB> {
         self.as_slice().bbox()
     }
+
+    fn lights(&self, out: &mut Vec<Box<dyn Light>>) {
+        self.as_slice().lights(out)
+    }
+
+    fn shadow_hit(&self, ray: &Ray, t_min: f32, t_max: f32, tr: &mut f32) -> Option<Hit<'_>> {
+        shadow_hit_all(self, ray, t_min, t_max, tr)
+    }
+
+    fn hit_counted(&self, ray: &Ray, t_min: f32, t_max: f32, visits: &mut u64) -> Option<Hit<'_>> {
+        hit_counted_all(self, ray, t_min, t_max, visits)
+    }
+}
+
+/// [`Hittable::shadow_hit`] of a list of objects.
+pub(crate) fn shadow_hit_all<'a, T: Hittable>(
+    objs: &'a [T],
+    ray: &Ray,
+    t_min: f32,
+    mut t_max: f32,
+    tr: &mut f32,
+) -> Option<Hit<'a>> {
+    let mut closest = None;
+    for obj in objs {
+        if let Some(hit) = obj.shadow_hit(ray, t_min, t_max, tr) {
+            t_max = hit.t;
+            closest = Some(hit);
+        }
+    }
+    closest
+}
+
+/// [`Hittable::hit_counted`] of a list of objects.
+pub(crate) fn hit_counted_all<'a, T: Hittable>(
+    objs: &'a [T],
+    ray: &Ray,
+    t_min: f32,
+    mut t_max: f32,
+    visits: &mut u64,
+) -> Option<Hit<'a>> {
+    let mut closest = None;
+    for obj in objs {
+        if let Some(hit) = obj.hit_counted(ray, t_min, t_max, visits) {
+            t_max = hit.t;
+            closest = Some(hit);
+        }
+    }
+    closest
 }
 
+/// An intersection between a ray and a [`Hittable`].
+#[derive(Clone, Copy)]
 pub struct Hit<'a> {
     pub p: Vec3,
+    /// Unit normal used for shading, always facing against the incoming ray. It can be
+    /// interpolated across the surface, unlike `geometric_normal`.
     pub normal: Vec3,
+    /// Unit normal of the actual surface, facing the same side as `normal`.
+    pub geometric_normal: Vec3,
     pub t: f32,
+    /// Whether the ray hit the outside of the surface.
     pub front_face: bool,
+    /// Barycentric coordinates of `p` when a triangle was hit, weights of its three vertices.
+    pub barycentrics: Option<Vec3>,
+    /// Surface coordinates of `p`, used to look up textures.
+    pub uv: Vec2,
     pub mat: &'a dyn Material,
 }
 
@@ -93,8 +217,11 @@ impl<'a> Hit<'a> {
         Self {
             p,
             normal,
+            geometric_normal: normal,
             t,
             front_face,
+            barycentrics: None,
+            uv: Vec2::zero(),
             mat,
         }
     }
@@ -0,0 +1,213 @@
+use crate::aabb::AABB;
+use crate::hittable::triangle::pad_flat;
+use crate::hittable::{Hit, Hittable};
+use crate::light::{Light, QuadLight};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::utils::{v_max, v_min};
+use ultraviolet::{Vec2, Vec3};
+
+/// Parallelogram spanned by two edges from a corner, without a material so that several of
+/// them can share one.
+#[derive(Clone, Copy)]
+pub(crate) struct Parallelogram {
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    /// Plane equation `normal . p = d`.
+    d: f32,
+    /// Maps points of the plane to their coordinates along `u` and `v`.
+    w: Vec3,
+}
+
+impl Parallelogram {
+    pub(crate) fn new(origin: Vec3, u: Vec3, v: Vec3) -> Self {
+        let n = u.cross(v);
+        let normal = n.normalized();
+        Self {
+            origin,
+            u,
+            v,
+            normal,
+            d: normal.dot(origin),
+            w: n / n.dot(n),
+        }
+    }
+
+    /// Returns the distance along the ray and the coordinates of the hit point along the edges,
+    /// both in `[0, 1]`.
+    pub(crate) fn intersect(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, Vec2)> {
+        let denom = self.normal.dot(ray.dir);
+        // Parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - self.normal.dot(ray.pos)) / denom;
+        if !(t >= t_min && t <= t_max) {
+            return None;
+        }
+
+        let planar = ray.at(t) - self.origin;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        if !((0.0..=1.0).contains(&alpha) && (0.0..=1.0).contains(&beta)) {
+            return None;
+        }
+
+        Some((t, Vec2::new(alpha, beta)))
+    }
+
+    pub(crate) fn make_hit<'a>(
+        &self,
+        ray: &Ray,
+        t: f32,
+        uv: Vec2,
+        mat: &'a dyn Material,
+    ) -> Hit<'a> {
+        let mut hit = Hit::new(ray, ray.at(t), self.normal, t, mat);
+        hit.uv = uv;
+        hit
+    }
+
+    /// Point at coordinates `uv` along the edges.
+    pub(crate) fn at(&self, uv: Vec2) -> Vec3 {
+        self.origin + self.u * uv.x + self.v * uv.y
+    }
+
+    pub(crate) fn normal(&self) -> Vec3 {
+        self.normal
+    }
+
+    pub(crate) fn area(&self) -> f32 {
+        self.u.cross(self.v).mag()
+    }
+
+    pub(crate) fn bbox(&self) -> AABB {
+        let corners = [
+            self.origin,
+            self.origin + self.u,
+            self.origin + self.v,
+            self.origin + self.u + self.v,
+        ];
+        let lo = corners.iter().fold(corners[0], |acc, &x| v_min(acc, x));
+        let hi = corners.iter().fold(corners[0], |acc, &x| v_max(acc, x));
+        pad_flat(AABB::new(lo, hi))
+    }
+}
+
+/// Planar parallelogram with a corner at `origin` and edges `u` and `v`. Its normal is
+/// `u x v`, and the UV coordinates go from 0 to 1 along each edge.
+pub struct Quad<T: Material> {
+    shape: Parallelogram,
+    pub mat: T,
+}
+
+impl<T: Material> Quad<T> {
+    pub fn new(origin: Vec3, u: Vec3, v: Vec3, mat: T) -> Self {
+        Self {
+            shape: Parallelogram::new(origin, u, v),
+            mat,
+        }
+    }
+
+    /// Rectangle in the plane `z = k`, facing `+z`.
+    pub fn xy_rect(x: (f32, f32), y: (f32, f32), k: f32, mat: T) -> Self {
+        Self::new(
+            Vec3::new(x.0, y.0, k),
+            Vec3::new(x.1 - x.0, 0.0, 0.0),
+            Vec3::new(0.0, y.1 - y.0, 0.0),
+            mat,
+        )
+    }
+
+    /// Rectangle in the plane `y = k`, facing `+y`. U goes along `z` and V along `x`.
+    pub fn xz_rect(x: (f32, f32), z: (f32, f32), k: f32, mat: T) -> Self {
+        Self::new(
+            Vec3::new(x.0, k, z.0),
+            Vec3::new(0.0, 0.0, z.1 - z.0),
+            Vec3::new(x.1 - x.0, 0.0, 0.0),
+            mat,
+        )
+    }
+
+    /// Rectangle in the plane `x = k`, facing `+x`.
+    pub fn yz_rect(y: (f32, f32), z: (f32, f32), k: f32, mat: T) -> Self {
+        Self::new(
+            Vec3::new(k, y.0, z.0),
+            Vec3::new(0.0, y.1 - y.0, 0.0),
+            Vec3::new(0.0, 0.0, z.1 - z.0),
+            mat,
+        )
+    }
+}
+
+impl<T: Material> Hittable for Quad<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        let (t, uv) = self.shape.intersect(r, t_min, t_max)?;
+        Some(self.shape.make_hit(r, t, uv, &self.mat))
+    }
+
+    fn bbox(&self) -> Option<AABB> {
+        Some(self.shape.bbox())
+    }
+
+    fn lights(&self, out: &mut Vec<Box<dyn Light>>) {
+        if self.mat.is_emissive() {
+            out.push(Box::new(QuadLight(self.shape)));
+        }
+    }
+}
+
+/// Axis-aligned box made of six [`Quad`]-like faces, all facing outwards.
+pub struct Cuboid<T: Material> {
+    faces: [Parallelogram; 6],
+    bbox: AABB,
+    pub mat: T,
+}
+
+impl<T: Material> Cuboid<T> {
+    /// Box with opposite corners `a` and `b`.
+    pub fn new(a: Vec3, b: Vec3, mat: T) -> Self {
+        let lo = v_min(a, b);
+        let hi = v_max(a, b);
+        let d = hi - lo;
+        let dx = Vec3::new(d.x, 0.0, 0.0);
+        let dy = Vec3::new(0.0, d.y, 0.0);
+        let dz = Vec3::new(0.0, 0.0, d.z);
+
+        let faces = [
+            Parallelogram::new(Vec3::new(lo.x, lo.y, hi.z), dx, dy), // front
+            Parallelogram::new(Vec3::new(hi.x, lo.y, hi.z), -dz, dy), // right
+            Parallelogram::new(Vec3::new(hi.x, lo.y, lo.z), -dx, dy), // back
+            Parallelogram::new(Vec3::new(lo.x, lo.y, lo.z), dz, dy), // left
+            Parallelogram::new(Vec3::new(lo.x, hi.y, hi.z), dx, -dz), // top
+            Parallelogram::new(Vec3::new(lo.x, lo.y, lo.z), dx, dz), // bottom
+        ];
+
+        Self {
+            faces,
+            bbox: pad_flat(AABB::new(lo, hi)),
+            mat,
+        }
+    }
+}
+
+impl<T: Material> Hittable for Cuboid<T> {
+    fn hit(&self, r: &Ray, t_min: f32, mut t_max: f32) -> Option<Hit<'_>> {
+        let mut closest = None;
+        for face in &self.faces {
+            if let Some((t, uv)) = face.intersect(r, t_min, t_max) {
+                t_max = t;
+                closest = Some((face, t, uv));
+            }
+        }
+        let (face, t, uv) = closest?;
+        Some(face.make_hit(r, t, uv, &self.mat))
+    }
+
+    fn bbox(&self) -> Option<AABB> {
+        Some(self.bbox)
+    }
+}
@@ -1,9 +1,61 @@
 use crate::aabb::AABB;
 use crate::hittable::{Hit, Hittable};
+use crate::light::{Light, SphereLight};
 use crate::material::Material;
 use crate::ray::Ray;
-use ultraviolet::Vec3;
+use std::f32::consts::PI;
+use ultraviolet::{Vec2, Vec3};
 
+/// Longitude and latitude of a point of the unit sphere, both in `[0, 1]`. `u` starts at `-x`
+/// and goes around the `y` axis, `v` goes from the south to the north pole.
+pub fn sphere_uv(p: Vec3) -> Vec2 {
+    let theta = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z).atan2(p.x) + PI;
+    Vec2::new(phi / (2.0 * PI), theta / PI)
+}
+
+fn hit_sphere<'a>(
+    r: &Ray,
+    center: Vec3,
+    radius: f32,
+    mat: &'a dyn Material,
+    t_min: f32,
+    t_max: f32,
+) -> Option<Hit<'a>> {
+    let oc = r.pos - center;
+
+    let half_b = oc.dot(r.dir);
+    let c = oc.dot(oc) - radius * radius;
+
+    let discriminant = half_b * half_b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let root = discriminant.sqrt();
+    let mut t = -half_b - root;
+    if t < t_min || t > t_max {
+        t = -half_b + root;
+        if t < t_min || t > t_max {
+            return None;
+        }
+    }
+
+    let p = r.at(t);
+    let outward_normal = (p - center) / radius;
+    let mut hit = Hit::new(r, p, outward_normal, t, mat);
+    hit.uv = sphere_uv(outward_normal);
+    Some(hit)
+}
+
+fn sphere_bbox(center: Vec3, radius: f32) -> AABB {
+    AABB::new(
+        center - Vec3::broadcast(radius),
+        center + Vec3::broadcast(radius),
+    )
+}
+
+/// Sphere of given `center` and `radius`.
 pub struct Sphere<T: Material> {
     pub center: Vec3,
     pub radius: f32,
@@ -11,35 +63,53 @@ pub struct Sphere<T: Material> {
 }
 
 impl<T: Material> Hittable for Sphere<T> {
-    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<Hit> {
-        let oc = r.pos - self.center;
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        hit_sphere(r, self.center, self.radius, &self.mat, t_min, t_max)
+    }
 
-        let half_b = oc.dot(r.dir);
-        let c = oc.dot(oc) - self.radius * self.radius;
+    fn bbox(&self) -> Option<AABB> {
+        Some(sphere_bbox(self.center, self.radius))
+    }
 
-        let discriminant = half_b * half_b - c;
-        if discriminant < 0.0 {
-            return None;
+    fn lights(&self, out: &mut Vec<Box<dyn Light>>) {
+        if self.mat.is_emissive() {
+            out.push(Box::new(SphereLight {
+                center: self.center,
+                radius: self.radius,
+            }));
         }
+    }
+}
 
-        let root = discriminant.sqrt();
-        let mut t = -half_b - root;
-        if t < t_min || t > t_max {
-            t = -half_b + root;
-            if t < t_min || t > t_max {
-                return None;
-            }
+/// Sphere moving in a straight line from `center0` at `time0` to `center1` at `time1`. It
+/// stays at the end points outside of that interval, so its bounding box covers every instant.
+pub struct MovingSphere<T: Material> {
+    pub center0: Vec3,
+    pub center1: Vec3,
+    pub time0: f32,
+    pub time1: f32,
+    pub radius: f32,
+    pub mat: T,
+}
+
+impl<T: Material> MovingSphere<T> {
+    pub fn center(&self, time: f32) -> Vec3 {
+        if self.time1 <= self.time0 {
+            return self.center0;
         }
+        let s = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.center0 + (self.center1 - self.center0) * s
+    }
+}
 
-        let p = r.at(t);
-        let outward_normal = (p - self.center) / self.radius;
-        Some(Hit::new(r, p, outward_normal, t, &self.mat))
+impl<T: Material> Hittable for MovingSphere<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        hit_sphere(r, self.center(r.time), self.radius, &self.mat, t_min, t_max)
     }
 
     fn bbox(&self) -> Option<AABB> {
-        Some(AABB::new(
-            self.center - Vec3::broadcast(self.radius),
-            self.center + Vec3::broadcast(self.radius),
-        ))
+        let b0 = sphere_bbox(self.center0, self.radius);
+        let b1 = sphere_bbox(self.center1, self.radius);
+        Some(b0.extend(&b1))
     }
 }
@@ -0,0 +1,212 @@
+use crate::aabb::AABB;
+use crate::hittable::{Hit, Hittable};
+use crate::ray::Ray;
+use ultraviolet::{Mat4, Vec3, Vec4};
+
+/// Affine transform along with its inverse.
+#[derive(Clone, Copy, Debug)]
+pub struct Transform {
+    m: Mat4,
+    inv: Mat4,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+fn rotation(axis: usize, angle: f32) -> Mat4 {
+    let (s, c) = angle.sin_cos();
+    let (i, j) = ((axis + 1) % 3, (axis + 2) % 3);
+    let mut m = Mat4::identity();
+    m.cols[i][i] = c;
+    m.cols[i][j] = s;
+    m.cols[j][i] = -s;
+    m.cols[j][j] = c;
+    m
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            m: Mat4::identity(),
+            inv: Mat4::identity(),
+        }
+    }
+
+    /// Transform given by a matrix acting on homogeneous column vectors, which must be
+    /// invertible.
+    pub fn from_matrix(m: Mat4) -> Self {
+        Self {
+            m,
+            inv: m.inversed(),
+        }
+    }
+
+    pub fn translation(v: Vec3) -> Self {
+        Self {
+            m: Mat4::from_translation(v),
+            inv: Mat4::from_translation(-v),
+        }
+    }
+
+    /// Scales by a different factor along each axis, none of them can be zero.
+    pub fn scale(v: Vec3) -> Self {
+        Self {
+            m: Mat4::from_nonuniform_scale(Vec4::new(v.x, v.y, v.z, 1.0)),
+            inv: Mat4::from_nonuniform_scale(Vec4::new(1.0 / v.x, 1.0 / v.y, 1.0 / v.z, 1.0)),
+        }
+    }
+
+    /// Counterclockwise rotation of `angle` radians around the `x` axis, looking from `+x`.
+    pub fn rotation_x(angle: f32) -> Self {
+        Self {
+            m: rotation(0, angle),
+            inv: rotation(0, -angle),
+        }
+    }
+
+    /// Counterclockwise rotation of `angle` radians around the `y` axis, looking from `+y`.
+    pub fn rotation_y(angle: f32) -> Self {
+        Self {
+            m: rotation(1, angle),
+            inv: rotation(1, -angle),
+        }
+    }
+
+    /// Counterclockwise rotation of `angle` radians around the `z` axis, looking from `+z`.
+    pub fn rotation_z(angle: f32) -> Self {
+        Self {
+            m: rotation(2, angle),
+            inv: rotation(2, -angle),
+        }
+    }
+
+    /// Applies `self`, then `other`.
+    pub fn then(&self, other: &Transform) -> Self {
+        Self {
+            m: other.m * self.m,
+            inv: self.inv * other.inv,
+        }
+    }
+
+    pub fn inversed(&self) -> Self {
+        Self {
+            m: self.inv,
+            inv: self.m,
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        self.m
+    }
+
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        (self.m * Vec4::new(p.x, p.y, p.z, 1.0)).xyz()
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        (self.m * Vec4::new(v.x, v.y, v.z, 0.0)).xyz()
+    }
+
+    /// Normals are transformed by the inverse transpose, to stay orthogonal to the surface.
+    /// The result is not normalized.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        (self.inv.transposed() * Vec4::new(n.x, n.y, n.z, 0.0)).xyz()
+    }
+
+    /// Bounding box of the transformed corners of `bbox`.
+    pub fn bbox(&self, bbox: &AABB) -> AABB {
+        let mut out: Option<AABB> = None;
+        for i in 0..8 {
+            let corner = Vec3::new(
+                if i & 1 == 0 { bbox.lo.x } else { bbox.hi.x },
+                if i & 2 == 0 { bbox.lo.y } else { bbox.hi.y },
+                if i & 4 == 0 { bbox.lo.z } else { bbox.hi.z },
+            );
+            let p = self.point(corner);
+            let b = AABB::new(p, p);
+            out = Some(out.map_or(b, |x| x.extend(&b)));
+        }
+        out.unwrap()
+    }
+}
+
+/// An object placed in the scene by a [`Transform`] from its own coordinates. Wrapping an
+/// `Arc<dyn Hittable>` shares the object between several instances, so a mesh or a [`BVH`]
+/// can be repeated many times for the memory of one.
+///
+/// [`BVH`]: crate::bvh::BVH
+pub struct Transformed<H: Hittable> {
+    object: H,
+    transform: Transform,
+    bbox: Option<AABB>,
+}
+
+impl<H: Hittable> Transformed<H> {
+    pub fn new(object: H, transform: Transform) -> Self {
+        let bbox = object.bbox().map(|b| transform.bbox(&b));
+        Self {
+            object,
+            transform,
+            bbox,
+        }
+    }
+
+    pub fn object(&self) -> &H {
+        &self.object
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// `r` in the coordinates of the object, and the factor its distances are scaled by.
+    fn local_ray(&self, r: &Ray) -> (Ray, f32) {
+        let to_object = self.transform.inversed();
+        let dir = to_object.vector(r.dir);
+
+        // Directions are normalized, so distances are scaled by the transform.
+        let scale = dir.mag();
+        let local = Ray::with_time(to_object.point(r.pos), dir / scale, r.time);
+        (local, scale)
+    }
+
+    /// Brings a hit along the ray returned by `local_ray` back to the world.
+    fn to_world<'a>(&self, r: &Ray, scale: f32, mut hit: Hit<'a>) -> Hit<'a> {
+        hit.t /= scale;
+        hit.p = r.at(hit.t);
+        hit.normal = self.transform.normal(hit.normal).normalized();
+        hit.geometric_normal = self.transform.normal(hit.geometric_normal).normalized();
+        hit
+    }
+}
+
+impl<H: Hittable> Hittable for Transformed<H> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        let (local, scale) = self.local_ray(r);
+        let hit = self.object.hit(&local, t_min * scale, t_max * scale)?;
+        Some(self.to_world(r, scale, hit))
+    }
+
+    fn bbox(&self) -> Option<AABB> {
+        self.bbox
+    }
+
+    fn shadow_hit(&self, r: &Ray, t_min: f32, t_max: f32, tr: &mut f32) -> Option<Hit<'_>> {
+        let (local, scale) = self.local_ray(r);
+        let hit = self
+            .object
+            .shadow_hit(&local, t_min * scale, t_max * scale, tr)?;
+        Some(self.to_world(r, scale, hit))
+    }
+
+    fn hit_counted(&self, r: &Ray, t_min: f32, t_max: f32, visits: &mut u64) -> Option<Hit<'_>> {
+        let (local, scale) = self.local_ray(r);
+        let hit = self
+            .object
+            .hit_counted(&local, t_min * scale, t_max * scale, visits)?;
+        Some(self.to_world(r, scale, hit))
+    }
+}
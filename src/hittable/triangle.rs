@@ -0,0 +1,241 @@
+use crate::aabb::AABB;
+use crate::hittable::{Hit, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::utils::{v_max, v_min};
+use ultraviolet::{Vec2, Vec3};
+
+/// Flat triangles would have a zero-thickness bounding box along one axis.
+const BBOX_PADDING: f32 = 1e-4;
+
+/// Pads flat bounding boxes, relative to the magnitude of their coordinates so that the
+/// padding is not lost to rounding far from the origin.
+pub(crate) fn pad_flat(bbox: AABB) -> AABB {
+    let scale = v_max(bbox.lo.abs(), bbox.hi.abs()).component_max();
+    bbox.padded(BBOX_PADDING * scale.max(1.0))
+}
+
+/// Watertight ray/triangle intersection (Woop, Benthin and Wald 2013): rays going exactly
+/// through a shared edge or vertex hit at least one of the adjacent triangles.
+///
+/// Returns the distance along the ray and the barycentric coordinates of the hit point.
+pub fn intersect(ray: &Ray, p: [Vec3; 3], t_min: f32, t_max: f32) -> Option<(f32, Vec3)> {
+    let d = ray.dir;
+    let abs_d = d.abs();
+    let kz = if abs_d.x > abs_d.y {
+        if abs_d.x > abs_d.z {
+            0
+        } else {
+            2
+        }
+    } else if abs_d.y > abs_d.z {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if d[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    let sx = d[kx] / d[kz];
+    let sy = d[ky] / d[kz];
+    let sz = 1.0 / d[kz];
+
+    let a = p[0] - ray.pos;
+    let b = p[1] - ray.pos;
+    let c = p[2] - ray.pos;
+
+    let ax = a[kx] - sx * a[kz];
+    let ay = a[ky] - sy * a[kz];
+    let bx = b[kx] - sx * b[kz];
+    let by = b[ky] - sy * b[kz];
+    let cx = c[kx] - sx * c[kz];
+    let cy = c[ky] - sy * c[kz];
+
+    let mut u = cx * by - cy * bx;
+    let mut v = ax * cy - ay * cx;
+    let mut w = bx * ay - by * ax;
+
+    // Edge cases are decided in double precision so that neighbouring triangles agree.
+    if u == 0.0 || v == 0.0 || w == 0.0 {
+        u = (cx as f64 * by as f64 - cy as f64 * bx as f64) as f32;
+        v = (ax as f64 * cy as f64 - ay as f64 * cx as f64) as f32;
+        w = (bx as f64 * ay as f64 - by as f64 * ax as f64) as f32;
+    }
+
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    let az = sz * a[kz];
+    let bz = sz * b[kz];
+    let cz = sz * c[kz];
+
+    let inv_det = 1.0 / det;
+    let t = (u * az + v * bz + w * cz) * inv_det;
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    Some((t, Vec3::new(u, v, w) * inv_det))
+}
+
+/// Builds the hit record of a triangle, interpolating the vertex normals and texture
+/// coordinates if there are any. Without texture coordinates, the UV coordinates of the hit are
+/// the barycentric weights of the second and third vertices.
+pub(crate) fn make_hit<'a>(
+    ray: &Ray,
+    t: f32,
+    bary: Vec3,
+    p: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[Vec2; 3]>,
+    mat: &'a dyn Material,
+) -> Hit<'a> {
+    let geometric = (p[1] - p[0]).cross(p[2] - p[0]).normalized();
+    let mut hit = Hit::new(ray, ray.at(t), geometric, t, mat);
+
+    if let Some(n) = normals {
+        let shading = n[0] * bary.x + n[1] * bary.y + n[2] * bary.z;
+        if shading.mag_sq() > 0.0 {
+            let shading = shading.normalized();
+            hit.normal = if hit.front_face { shading } else { -shading };
+        }
+    }
+
+    hit.uv = match uvs {
+        Some(uv) => uv[0] * bary.x + uv[1] * bary.y + uv[2] * bary.z,
+        None => Vec2::new(bary.y, bary.z),
+    };
+    hit.barycentrics = Some(bary);
+    hit
+}
+
+pub(crate) fn bbox(p: [Vec3; 3]) -> AABB {
+    pad_flat(AABB::new(
+        v_min(v_min(p[0], p[1]), p[2]),
+        v_max(v_max(p[0], p[1]), p[2]),
+    ))
+}
+
+/// A single triangle, with optional per-vertex normals for smooth shading and texture
+/// coordinates.
+pub struct Triangle<T: Material> {
+    pub vertices: [Vec3; 3],
+    pub normals: Option<[Vec3; 3]>,
+    pub uvs: Option<[Vec2; 3]>,
+    pub mat: T,
+}
+
+impl<T: Material> Triangle<T> {
+    pub fn new(vertices: [Vec3; 3], mat: T) -> Self {
+        Self {
+            vertices,
+            normals: None,
+            uvs: None,
+            mat,
+        }
+    }
+}
+
+impl<T: Material> Hittable for Triangle<T> {
+    fn hit(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<Hit<'_>> {
+        let (t, bary) = intersect(r, self.vertices, t_min, t_max)?;
+        Some(make_hit(
+            r,
+            t,
+            bary,
+            self.vertices,
+            self.normals,
+            self.uvs,
+            &self.mat,
+        ))
+    }
+
+    fn bbox(&self) -> Option<AABB> {
+        Some(bbox(self.vertices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).mag() < 1e-5
+    }
+
+    #[test]
+    fn rays_through_shared_edges_and_vertices_hit() {
+        // A fan of triangles around the origin, which is a vertex of all of them.
+        let ring: Vec<Vec3> = (0..7)
+            .map(|k| {
+                let a = k as f32 * std::f32::consts::TAU / 7.0;
+                Vec3::new(a.cos(), a.sin(), 0.0)
+            })
+            .collect();
+        let fan: Vec<[Vec3; 3]> = (0..7)
+            .map(|k| [Vec3::zero(), ring[k], ring[(k + 1) % 7]])
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..2000 {
+            // Through the shared vertex, or a point of a shared edge.
+            let target = match rng.gen_range(0, 4) {
+                0 => Vec3::zero(),
+                _ => ring[rng.gen_range(0, 7)] * rng.gen_range(0.0, 1.0),
+            };
+            let pos = Vec3::new(
+                rng.gen_range(-3.0, 3.0),
+                rng.gen_range(-3.0, 3.0),
+                rng.gen_range(0.5, 3.0) * if rng.gen() { 1.0 } else { -1.0 },
+            );
+            let ray = Ray::new(pos, target - pos);
+            let hits = fan
+                .iter()
+                .filter(|&&p| intersect(&ray, p, 0.0, f32::INFINITY).is_some())
+                .count();
+            assert!(hits > 0, "ray from {:?} to {:?} went through", pos, target);
+        }
+    }
+
+    #[test]
+    fn hit_interpolates_normals_and_uvs() {
+        let mut tri = Triangle::new(
+            [Vec3::zero(), Vec3::unit_x(), Vec3::unit_y()],
+            Lambertian::new(Vec3::one()),
+        );
+        tri.normals = Some([Vec3::unit_z(), Vec3::unit_z(), Vec3::unit_x()]);
+        tri.uvs = Some([Vec2::new(1.0, 0.0), Vec2::new(1.0, 1.0), Vec2::zero()]);
+
+        let ray = Ray::new(Vec3::new(0.25, 0.5, 2.0), -Vec3::unit_z());
+        let hit = tri.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        let bary = hit.barycentrics.unwrap();
+        assert!((bary.x + bary.y + bary.z - 1.0).abs() < 1e-6);
+        assert!(close(bary, Vec3::new(0.25, 0.25, 0.5)));
+        assert!((hit.t - 2.0).abs() < 1e-6);
+        assert!(close(hit.p, Vec3::new(0.25, 0.5, 0.0)));
+
+        assert!(hit.front_face);
+        assert!(close(hit.geometric_normal, Vec3::unit_z()));
+        assert!(close(hit.normal, Vec3::new(1.0, 0.0, 1.0).normalized()));
+        assert!((hit.uv.x - 0.5).abs() < 1e-6 && (hit.uv.y - 0.25).abs() < 1e-6);
+
+        // From below, both normals face the ray.
+        let ray = Ray::new(Vec3::new(0.25, 0.5, -1.0), Vec3::unit_z());
+        let hit = tri.hit(&ray, 0.0, f32::INFINITY).unwrap();
+        assert!(!hit.front_face);
+        assert!(close(hit.geometric_normal, -Vec3::unit_z()));
+        assert!(close(hit.normal, -Vec3::new(1.0, 0.0, 1.0).normalized()));
+    }
+}
@@ -1,81 +1,171 @@
 use crate::hittable::Hittable;
-use crate::raytrace::RayTracer;
+use crate::raytrace::{vec3, Color, RayTracer};
+use crate::tonemap::ToneMapper;
 use rayon::prelude::*;
 
+/// Progressively renders a [`RayTracer`], accumulating one sample per pixel on every call to
+/// [`get_next`](ImageProvider::get_next).
+///
+/// Samples are summed in linear space and in double precision, so that long renders keep
+/// converging and values above 1 are preserved. They are only converted to a displayable RGBA8
+/// buffer afterwards, using [`tone_mapper`](ImageProvider::tone_mapper).
+///
+/// NaN and infinite samples are discarded, so each pixel is averaged over its own number of
+/// valid samples and does not get darker than the others for having produced one.
 pub struct ImageProvider {
-    acc: Vec<u16>,
+    pub tone_mapper: ToneMapper,
+    acc: Vec<f64>,
+    /// Number of valid samples summed in `acc`, per pixel.
+    counts: Vec<u32>,
     pixels: Vec<u8>,
+    width: usize,
     samples: u32,
 }
 
+impl Default for ImageProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ImageProvider {
     pub fn new() -> Self {
         Self {
+            tone_mapper: ToneMapper::default(),
             acc: vec![],
+            counts: vec![],
             pixels: vec![],
+            width: 0,
             samples: 0,
         }
     }
 
+    /// Discards the accumulated samples, to be called whenever the camera or scene changes.
     pub fn moved(&mut self) {
         for v in &mut self.acc {
-            *v = 0;
+            *v = 0.0;
+        }
+        for n in &mut self.counts {
+            *n = 0;
         }
         self.samples = 0;
     }
 
+    /// Number of samples per pixel traced so far, including the discarded ones.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        if self.width == 0 {
+            0
+        } else {
+            self.pixels.len() / (self.width * 4)
+        }
+    }
+
+    /// The current image, as RGBA8 rows from bottom to top.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// The current image in linear RGB, averaged over the samples, rows from bottom to top.
+    pub fn linear(&self) -> Vec<Color> {
+        self.acc
+            .chunks_exact(3)
+            .zip(&self.counts)
+            .map(|(rgb, &n)| {
+                let inv = 1.0 / n.max(1) as f64;
+                vec3(
+                    (rgb[0] * inv) as f32,
+                    (rgb[1] * inv) as f32,
+                    (rgb[2] * inv) as f32,
+                )
+            })
+            .collect()
+    }
+
     pub fn resize(&mut self, width: usize, height: usize) {
-        self.acc = vec![0; width * height * 3];
+        self.acc = vec![0.0; width * height * 3];
+        self.counts = vec![0; width * height];
         self.pixels = vec![0xFF; width * height * 4];
+        self.width = width;
         self.samples = 0;
-        println!("\nresized to {} {}", width, height)
     }
 
+    /// Converts the accumulated samples to the displayed RGBA8 buffer, to be called after
+    /// changing the tone mapper.
+    pub fn update_pixels(&mut self) {
+        if self.width == 0 {
+            return;
+        }
+
+        let width = self.width;
+        let tone_mapper = self.tone_mapper;
+
+        self.acc
+            .par_chunks_exact(width * 3)
+            .zip(self.counts.par_chunks_exact(width))
+            .zip(self.pixels.par_chunks_exact_mut(width * 4))
+            .for_each(move |((acc, counts), line)| {
+                let pixels = acc.chunks_exact(3).zip(counts);
+                for ((rgb, &n), rgba) in pixels.zip(line.chunks_exact_mut(4)) {
+                    let inv = 1.0 / n.max(1) as f64;
+                    let c = vec3(
+                        (rgb[0] * inv) as f32,
+                        (rgb[1] * inv) as f32,
+                        (rgb[2] * inv) as f32,
+                    );
+                    rgba[..3].copy_from_slice(&tone_mapper.to_srgb8(c));
+                }
+            });
+    }
+
+    /// Traces one more sample per pixel at the given resolution and returns the updated image.
+    /// Changing the resolution resets the accumulation.
     pub fn get_next<T: Hittable + Sync>(
         &mut self,
         tracer: &mut RayTracer<T>,
         width: usize,
         height: usize,
     ) -> &[u8] {
-        if width * height * 4 != self.pixels.len() {
+        if width * height * 4 != self.pixels.len() || width != self.width {
             self.resize(width, height);
         }
 
         tracer.cam.aspect_ratio = (width as f32) / height as f32;
         tracer.init();
 
-        let w = 1.0 / width as f32;
-        let h = 1.0 / height as f32;
-
         let tracer = &tracer;
+        let sample = self.samples;
         self.samples += 1;
-        let samples = self.samples as u16;
 
         self.acc
             .as_mut_slice()
             .par_chunks_exact_mut(width * 3)
-            .zip(self.pixels.par_chunks_exact_mut(width * 4))
+            .zip(self.counts.par_chunks_exact_mut(width))
             .enumerate()
-            .for_each(move |(y, (acc, line))| {
-                for (x, (rgb, rgba)) in acc
-                    .chunks_exact_mut(3)
-                    .zip(line.chunks_exact_mut(4))
-                    .enumerate()
-                {
-                    let c = tracer.get_sample(x as f32 * w, y as f32 * h, w, h);
-
-                    unsafe {
-                        *rgb.get_unchecked_mut(0) += (c.x.sqrt() * 255.999) as u16;
-                        *rgb.get_unchecked_mut(1) += (c.y.sqrt() * 255.999) as u16;
-                        *rgb.get_unchecked_mut(2) += (c.z.sqrt() * 255.999) as u16;
-
-                        *rgba.get_unchecked_mut(0) = (rgb.get_unchecked(0) / samples) as u8;
-                        *rgba.get_unchecked_mut(1) = (rgb.get_unchecked(1) / samples) as u8;
-                        *rgba.get_unchecked_mut(2) = (rgb.get_unchecked(2) / samples) as u8;
+            .for_each(move |(y, (acc, counts))| {
+                let pixels = acc.chunks_exact_mut(3).zip(counts);
+                for (x, (rgb, n)) in pixels.enumerate() {
+                    let c = tracer.get_sample(x, y, width, height, sample);
+
+                    // A single NaN or infinite sample would ruin the pixel for good, it is left
+                    // out of the average instead.
+                    if c.x.is_finite() && c.y.is_finite() && c.z.is_finite() {
+                        rgb[0] += c.x as f64;
+                        rgb[1] += c.y as f64;
+                        rgb[2] += c.z as f64;
+                        *n += 1;
                     }
                 }
             });
 
+        self.update_pixels();
         &self.pixels
     }
 }
@@ -0,0 +1,32 @@
+use super::{Integrator, Scene};
+use crate::ray::Ray;
+use crate::raytrace::Color;
+use crate::utils::{random_unit_vector, SampleRng};
+
+/// Ambient occlusion: surfaces are white, darkened where other objects closer than `distance`
+/// hide the sky above them. Materials and lights are ignored.
+pub struct AmbientOcclusion {
+    pub distance: f32,
+}
+
+impl Integrator for AmbientOcclusion {
+    fn name(&self) -> &'static str {
+        "ao"
+    }
+
+    fn radiance(&self, scene: &Scene<'_>, ray: Ray, rng: &mut SampleRng) -> Color {
+        let hit = match scene.hit(&ray) {
+            Some(hit) => hit,
+            None => return Color::one(),
+        };
+
+        // Cosine weighted, so the fraction of unoccluded rays is the irradiance from a white sky.
+        let dir = (hit.normal + random_unit_vector(rng)).normalized();
+        let probe = Ray::with_time(hit.p, dir, ray.time);
+        if scene.world.hit(&probe, 0.001, self.distance).is_some() {
+            Color::zero()
+        } else {
+            Color::one()
+        }
+    }
+}
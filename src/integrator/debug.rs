@@ -0,0 +1,92 @@
+use super::{Integrator, Scene};
+use crate::ray::Ray;
+use crate::raytrace::{vec3, Color};
+use crate::utils::{splitmix64, SampleRng};
+
+/// Number of visited nodes shown in white by [`BvhCost`].
+const MAX_COST: f32 = 128.0;
+
+/// Shows a property of the surfaces seen by the camera instead of their lighting. Rays that
+/// leave the scene are black.
+#[derive(Clone, Copy, Debug)]
+pub enum DebugView {
+    /// Shading normal, with each coordinate mapped from `[-1, 1]` to `[0, 1]`.
+    ShadingNormal,
+    /// Normal of the actual surface, mapped like `ShadingNormal`.
+    GeometricNormal,
+    /// Gray level falling off with the distance, mid gray at `scale`.
+    Depth { scale: f32 },
+    /// Green where the outside of surfaces is seen, red where the inside is.
+    FrontFace,
+    /// A random color for every material.
+    Material,
+}
+
+fn map_normal(n: ultraviolet::Vec3) -> Color {
+    0.5 * (n + Color::one())
+}
+
+/// Bright color picked by `id`.
+fn id_color(id: usize) -> Color {
+    let h = splitmix64(id as u64);
+    let c = |shift: u32| 0.2 + 0.8 * ((h >> shift) & 0xFF) as f32 / 255.0;
+    vec3(c(0), c(8), c(16))
+}
+
+fn heat(t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    vec3(
+        (3.0 * t).min(1.0),
+        (3.0 * t - 1.0).clamp(0.0, 1.0),
+        if t < 1.0 / 3.0 {
+            1.0 - 3.0 * t
+        } else {
+            (3.0 * t - 2.0).max(0.0)
+        },
+    )
+}
+
+impl Integrator for DebugView {
+    fn name(&self) -> &'static str {
+        match self {
+            DebugView::ShadingNormal => "shading-normal",
+            DebugView::GeometricNormal => "geometric-normal",
+            DebugView::Depth { .. } => "depth",
+            DebugView::FrontFace => "front-face",
+            DebugView::Material => "material",
+        }
+    }
+
+    fn radiance(&self, scene: &Scene<'_>, ray: Ray, _rng: &mut SampleRng) -> Color {
+        let hit = match scene.hit(&ray) {
+            Some(hit) => hit,
+            None => return Color::zero(),
+        };
+        match *self {
+            DebugView::ShadingNormal => map_normal(hit.normal),
+            DebugView::GeometricNormal => map_normal(hit.geometric_normal),
+            DebugView::Depth { scale } => Color::broadcast(scale / (hit.t + scale)),
+            DebugView::FrontFace if hit.front_face => vec3(0.0, 1.0, 0.0),
+            DebugView::FrontFace => vec3(1.0, 0.0, 0.0),
+            DebugView::Material => id_color(hit.mat.id()),
+        }
+    }
+}
+
+/// Shows the number of hierarchy nodes visited to find the closest hit, from blue through red
+/// to white. Rays that leave the scene are shown too, since they can be as costly.
+pub struct BvhCost;
+
+impl Integrator for BvhCost {
+    fn name(&self) -> &'static str {
+        "bvh-cost"
+    }
+
+    fn radiance(&self, scene: &Scene<'_>, ray: Ray, _rng: &mut SampleRng) -> Color {
+        let mut visits = 0;
+        scene
+            .world
+            .hit_counted(&ray, 0.001, f32::INFINITY, &mut visits);
+        heat(visits as f32 / MAX_COST)
+    }
+}
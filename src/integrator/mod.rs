@@ -0,0 +1,138 @@
+//! Integrators compute the light reaching the camera along a ray: by path tracing, by simpler
+//! approximations, or as views of the properties of the scene for debugging.
+
+mod ao;
+mod debug;
+mod path;
+
+pub use ao::AmbientOcclusion;
+pub use debug::{BvhCost, DebugView};
+pub use path::{Bounces, DirectLighting, MaxDepth, PathHook, PathState, PathTracer};
+
+use crate::hittable::{Hit, Hittable};
+use crate::light::Light;
+use crate::ray::Ray;
+use crate::raytrace::{Background, Color};
+use crate::utils::SampleRng;
+use rand::Rng;
+use std::sync::Arc;
+
+/// What integrators see of a [`RayTracer`](crate::raytrace::RayTracer).
+#[derive(Clone, Copy)]
+pub struct Scene<'a> {
+    pub world: &'a dyn Hittable,
+    /// Lights sampled at every diffuse bounce, collected from the world.
+    pub lights: &'a [Box<dyn Light>],
+    pub background: &'a Background,
+    pub max_depth: MaxDepth,
+}
+
+impl<'a> Scene<'a> {
+    /// Closest intersection along `ray`, ignoring the surface it starts from.
+    pub fn hit(&self, ray: &Ray) -> Option<Hit<'a>> {
+        self.world.hit(ray, 0.001, f32::INFINITY)
+    }
+
+    /// Probability density of sampling the direction of `ray` towards the light it hits, `None`
+    /// if `hit` does not lie on one of the sampled lights.
+    pub fn light_pdf(&self, ray: &Ray, hit: &Hit<'_>) -> Option<f32> {
+        self.lights
+            .iter()
+            .find_map(|light| match light.hit_pdf(ray) {
+                Some((t, pdf)) if (t - hit.t).abs() <= 1e-3 * hit.t => {
+                    Some(pdf / self.lights.len() as f32)
+                }
+                _ => None,
+            })
+    }
+
+    /// Light reaching `hit` directly from a random light, and scattered back along `ray`. It is
+    /// weighted against the chance of finding the light by scattering instead.
+    pub fn sample_light(&self, ray: &Ray, hit: &Hit<'_>, rng: &mut SampleRng) -> Color {
+        let light = &self.lights[rng.gen_range(0, self.lights.len())];
+        let sample = match light.sample(hit.p, rng) {
+            Some(x) => x,
+            None => return Color::zero(),
+        };
+        let f = hit.mat.eval(ray, hit, sample.dir);
+        if f.component_max() <= 0.0 {
+            return Color::zero();
+        }
+
+        // The light is found where it was sampled unless something is in the way, participating
+        // media dim it instead.
+        let shadow = Ray::with_time(hit.p, sample.dir, ray.time);
+        let eps = 1e-3 * sample.dist;
+        let mut tr = 1.0;
+        match self
+            .world
+            .shadow_hit(&shadow, 0.001, sample.dist + eps, &mut tr)
+        {
+            Some(h) if h.t >= sample.dist - eps && tr > 0.0 => {
+                let pdf = sample.pdf / self.lights.len() as f32;
+                let weight = power_heuristic(pdf, hit.mat.pdf(ray, hit, sample.dir));
+                f * h.mat.emitted(&shadow, &h) * (tr * weight / pdf)
+            }
+            _ => Color::zero(),
+        }
+    }
+}
+
+/// Computes the color of camera rays.
+pub trait Integrator: Send + Sync {
+    /// Name accepted by [`from_name`].
+    fn name(&self) -> &'static str;
+
+    /// Light reaching the camera along `ray`, or any color the integrator shows instead.
+    fn radiance(&self, scene: &Scene<'_>, ray: Ray, rng: &mut SampleRng) -> Color;
+}
+
+/// Weight of a sample drawn with the density `a` when the same path could also be sampled with
+/// the density `b`, favoring whichever is more likely.
+fn power_heuristic(a: f32, b: f32) -> f32 {
+    let (a2, b2) = (a * a, b * b);
+    if a2 + b2 > 0.0 {
+        a2 / (a2 + b2)
+    } else {
+        0.0
+    }
+}
+
+/// Names of the integrators, in the order the viewer cycles through them.
+pub const NAMES: [&str; 9] = [
+    "path",
+    "direct",
+    "ao",
+    "shading-normal",
+    "geometric-normal",
+    "depth",
+    "front-face",
+    "material",
+    "bvh-cost",
+];
+
+/// Creates the integrator called `name`. `scale` is the typical distance to the objects in
+/// view, such as [`Camera::focus_dist`](crate::camera::Camera::focus_dist): ambient occlusion
+/// looks for occluders within a quarter of it, and the depth view is mid gray at that distance.
+pub fn from_name(name: &str, scale: f32) -> Result<Arc<dyn Integrator>, String> {
+    Ok(match name {
+        "path" => Arc::new(PathTracer),
+        "direct" => Arc::new(DirectLighting),
+        "ao" => Arc::new(AmbientOcclusion {
+            distance: 0.25 * scale,
+        }),
+        "shading-normal" => Arc::new(DebugView::ShadingNormal),
+        "geometric-normal" => Arc::new(DebugView::GeometricNormal),
+        "depth" => Arc::new(DebugView::Depth { scale }),
+        "front-face" => Arc::new(DebugView::FrontFace),
+        "material" => Arc::new(DebugView::Material),
+        "bvh-cost" => Arc::new(BvhCost),
+        _ => {
+            return Err(format!(
+                "unknown integrator `{}`, expected one of {}",
+                name,
+                NAMES.join(", ")
+            ))
+        }
+    })
+}
@@ -0,0 +1,308 @@
+use super::{power_heuristic, Integrator, Scene};
+use crate::hittable::Hit;
+use crate::material::{ScatterKind, ScatterResult};
+use crate::ray::Ray;
+use crate::raytrace::Color;
+use crate::utils::SampleRng;
+use rand::Rng;
+use std::str::FromStr;
+
+/// Limits on the number of times a path scatters, for each [`ScatterKind`].
+#[derive(Clone, Copy, Debug)]
+pub struct MaxDepth {
+    pub diffuse: u32,
+    pub specular: u32,
+    pub transmission: u32,
+    /// Number of bounces after which paths are terminated at random by Russian roulette, the
+    /// more likely the less light they carry. Surviving paths are brightened to compensate, so
+    /// the image is as bright on average.
+    pub roulette: u32,
+}
+
+impl Default for MaxDepth {
+    fn default() -> Self {
+        Self {
+            diffuse: 4,
+            specular: 8,
+            transmission: 12,
+            roulette: 3,
+        }
+    }
+}
+
+impl FromStr for MaxDepth {
+    type Err = String;
+
+    /// Parses one limit for every kind of bounce, or three separated by commas for diffuse,
+    /// specular and transmission bounces.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let limits = s
+            .split(',')
+            .map(|x| x.trim().parse())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|_| format!("invalid depth `{}`", s))?;
+        let (diffuse, specular, transmission) = match *limits.as_slice() {
+            [n] => (n, n, n),
+            [d, s, t] => (d, s, t),
+            _ => return Err(format!("expected 1 or 3 depths, got `{}`", s)),
+        };
+        Ok(Self {
+            diffuse,
+            specular,
+            transmission,
+            ..Self::default()
+        })
+    }
+}
+
+/// Number of times a path scattered so far, for each [`ScatterKind`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bounces {
+    pub diffuse: u32,
+    pub specular: u32,
+    pub transmission: u32,
+}
+
+impl Bounces {
+    pub fn total(&self) -> u32 {
+        self.diffuse + self.specular + self.transmission
+    }
+
+    /// Adds a bounce of the given kind, `None` if it exceeds the limit.
+    fn add(mut self, kind: ScatterKind, max: &MaxDepth) -> Option<Self> {
+        let (count, limit) = match kind {
+            ScatterKind::Diffuse => (&mut self.diffuse, max.diffuse),
+            ScatterKind::Specular => (&mut self.specular, max.specular),
+            ScatterKind::Transmission => (&mut self.transmission, max.transmission),
+        };
+        if *count >= limit {
+            return None;
+        }
+        *count += 1;
+        Some(self)
+    }
+}
+
+/// A path being traced from the camera, one bounce at a time.
+pub struct PathState {
+    /// Current segment of the path.
+    pub ray: Ray,
+    /// Light found so far, as it reaches the camera.
+    pub radiance: Color,
+    /// Fraction of the light arriving along `ray` that reaches the camera.
+    pub throughput: Color,
+    pub bounces: Bounces,
+    /// Probability density of the direction of `ray` if lights were also sampled at its origin,
+    /// in which case the light it hits is weighted against that.
+    scatter_pdf: Option<f32>,
+}
+
+/// Called at every bounce of the paths traced by [`PathTracer::trace`], to gather statistics or
+/// extra outputs without changing the path tracer.
+pub trait PathHook {
+    /// The ray of `state` hit something, before its light is added and it is scattered.
+    fn hit(&mut self, _state: &PathState, _hit: &Hit<'_>) {}
+    /// The ray of `state` left the scene.
+    fn escape(&mut self, _state: &PathState) {}
+}
+
+/// Does nothing.
+impl PathHook for () {}
+
+/// Unbiased path tracer: rays bounce around the scene until they are absorbed, leave it or
+/// reach the [`MaxDepth`], and lights are sampled at every diffuse bounce.
+pub struct PathTracer;
+
+impl PathTracer {
+    /// Light reaching the camera along `ray` by a random path. `hook` is called at every
+    /// bounce.
+    pub fn trace(
+        &self,
+        scene: &Scene<'_>,
+        ray: Ray,
+        rng: &mut SampleRng,
+        hook: &mut impl PathHook,
+    ) -> Color {
+        let mut state = PathState {
+            ray,
+            radiance: Color::zero(),
+            throughput: Color::one(),
+            bounces: Bounces::default(),
+            scatter_pdf: None,
+        };
+
+        loop {
+            let ray = &state.ray;
+            let hit = match scene.hit(ray) {
+                Some(hit) => hit,
+                None => {
+                    hook.escape(&state);
+                    return state.radiance + state.throughput * scene.background.color(ray);
+                }
+            };
+            hook.hit(&state, &hit);
+
+            // Hits can also be particles inside a volume, the scattered ray then starts within
+            // it and the volume is left or scattered again by the next hit.
+            let mut emitted = hit.mat.emitted(ray, &hit);
+            if let Some(pdf) = state.scatter_pdf {
+                if emitted.component_max() > 0.0 {
+                    if let Some(light_pdf) = scene.light_pdf(ray, &hit) {
+                        emitted *= power_heuristic(pdf, light_pdf);
+                    }
+                }
+            }
+            state.radiance += state.throughput * emitted;
+
+            let ScatterResult {
+                scattered,
+                mut attenuation,
+                kind,
+            } = match hit.mat.scatter(ray, hit, rng) {
+                Some(x) => x,
+                None => return state.radiance,
+            };
+            let bounces = match state.bounces.add(kind, &scene.max_depth) {
+                Some(x) => x,
+                None => return state.radiance,
+            };
+
+            let sample_lights = kind == ScatterKind::Diffuse && !scene.lights.is_empty();
+            if sample_lights {
+                state.radiance += state.throughput * scene.sample_light(ray, &hit, rng);
+            }
+            let scatter_pdf = if sample_lights {
+                Some(hit.mat.pdf(ray, &hit, scattered.dir))
+            } else {
+                None
+            };
+
+            if state.bounces.total() >= scene.max_depth.roulette {
+                let survival = (state.throughput * attenuation).component_max().min(0.95);
+                if rng.gen::<f32>() >= survival {
+                    return state.radiance;
+                }
+                attenuation /= survival;
+            }
+
+            state.ray = scattered;
+            state.throughput *= attenuation;
+            state.bounces = bounces;
+            state.scatter_pdf = scatter_pdf;
+        }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn name(&self) -> &'static str {
+        "path"
+    }
+
+    fn radiance(&self, scene: &Scene<'_>, ray: Ray, rng: &mut SampleRng) -> Color {
+        self.trace(scene, ray, rng, &mut ())
+    }
+}
+
+/// Only the light coming directly from light sources and the background, as seen through
+/// mirrors and glass: a [`PathTracer`] stopping at the second diffuse bounce.
+pub struct DirectLighting;
+
+impl Integrator for DirectLighting {
+    fn name(&self) -> &'static str {
+        "direct"
+    }
+
+    fn radiance(&self, scene: &Scene<'_>, ray: Ray, rng: &mut SampleRng) -> Color {
+        let scene = Scene {
+            max_depth: MaxDepth {
+                diffuse: 1,
+                roulette: u32::MAX,
+                ..scene.max_depth
+            },
+            ..*scene
+        };
+        PathTracer.trace(&scene, ray, rng, &mut ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::sphere::Sphere;
+    use crate::hittable::Hittable;
+    use crate::material::{Dielectric, Lambertian, Material, Metal};
+    use crate::raytrace::Background;
+    use crate::utils::{random_unit_vector, sample_rng};
+    use ultraviolet::Vec3;
+
+    /// Statistics of many paths: the largest number of bounces of each kind seen at a hit.
+    #[derive(Default)]
+    struct Stats {
+        max: Bounces,
+        hits: u32,
+        escapes: u32,
+    }
+
+    impl PathHook for Stats {
+        fn hit(&mut self, state: &PathState, _hit: &Hit<'_>) {
+            let b = state.bounces;
+            self.max.diffuse = self.max.diffuse.max(b.diffuse);
+            self.max.specular = self.max.specular.max(b.specular);
+            self.max.transmission = self.max.transmission.max(b.transmission);
+            self.hits += 1;
+        }
+
+        fn escape(&mut self, _state: &PathState) {
+            self.escapes += 1;
+        }
+    }
+
+    fn sphere(center: Vec3, radius: f32, mat: impl Material + 'static) -> Box<dyn Hittable> {
+        Box::new(Sphere {
+            center,
+            radius,
+            mat,
+        })
+    }
+
+    #[test]
+    fn bounces_stay_within_max_depth() {
+        // A mirror and a glass ball in a closed diffuse room, nothing ever escapes.
+        let world: Vec<Box<dyn Hittable>> = vec![
+            sphere(Vec3::zero(), 10.0, Lambertian::new(Color::broadcast(0.9))),
+            sphere(
+                Vec3::new(0.0, 0.0, -3.0),
+                1.0,
+                Metal::new(Color::one(), 0.0),
+            ),
+            sphere(Vec3::new(0.0, 0.0, 3.0), 1.0, Dielectric::new(1.5)),
+        ];
+        let max_depth = MaxDepth {
+            diffuse: 2,
+            specular: 3,
+            transmission: 4,
+            roulette: u32::MAX,
+        };
+        let scene = Scene {
+            world: &world,
+            lights: &[],
+            background: &Background::Sky,
+            max_depth,
+        };
+
+        let mut stats = Stats::default();
+        for i in 0..2000 {
+            let mut rng = sample_rng(0, i, 0, 0);
+            let ray = Ray::new(Vec3::zero(), random_unit_vector(&mut rng));
+            PathTracer.trace(&scene, ray, &mut rng, &mut stats);
+        }
+
+        assert_eq!(stats.escapes, 0);
+        assert!(stats.hits > 2000 * max_depth.diffuse);
+        // Paths are only stopped by diffuse bounces, once the last one is hit.
+        assert_eq!(stats.max.diffuse, max_depth.diffuse);
+        assert!(stats.max.specular <= max_depth.specular);
+        assert!(stats.max.transmission <= max_depth.transmission);
+        assert!(stats.max.specular > 0 && stats.max.transmission > 0);
+    }
+}
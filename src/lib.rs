@@ -0,0 +1,41 @@
+//! A CPU path tracer following the "Ray Tracing in One Weekend" series.
+//!
+//! A scene is any [`Hittable`](hittable::Hittable), usually a [`LinearBVH`](bvh::LinearBVH) of
+//! objects, wrapped in a [`RayTracer`](raytrace::RayTracer) which owns the
+//! [`Camera`](camera::Camera).
+//! [`ImageProvider`](image_gen::ImageProvider) then progressively renders it into an RGBA8
+//! buffer, one sample per pixel per call.
+//!
+//! ```
+//! use raytracing::hittable::Hittable;
+//! use raytracing::image_gen::ImageProvider;
+//! use raytracing::raytrace::RayTracer;
+//! use raytracing::scenes::random_scene;
+//!
+//! let mut tracer: RayTracer<Box<dyn Hittable>> = RayTracer::new(Box::new(random_scene(0)));
+//! let mut provider = ImageProvider::new();
+//!
+//! for _ in 0..4 {
+//!     provider.get_next(&mut tracer, 32, 18);
+//! }
+//! assert_eq!(provider.pixels().len(), 32 * 18 * 4);
+//! ```
+
+#![allow(clippy::upper_case_acronyms)]
+
+pub mod aabb;
+pub mod bvh;
+pub mod camera;
+pub mod hittable;
+pub mod image_gen;
+pub mod integrator;
+pub mod light;
+pub mod material;
+pub mod output;
+pub mod ray;
+pub mod raytrace;
+pub mod scenes;
+pub mod texture;
+pub mod tonemap;
+pub mod utils;
+pub mod volume;
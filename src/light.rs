@@ -0,0 +1,136 @@
+//! Light sources that can be sampled explicitly, so that small ones are found without relying
+//! on rays hitting them by chance.
+
+use crate::hittable::quad::Parallelogram;
+use crate::ray::Ray;
+use crate::utils::{orthonormal_basis, random_unit_vector, SampleRng};
+use rand::Rng;
+use std::f32::consts::PI;
+use ultraviolet::{Vec2, Vec3};
+
+/// A direction from a point towards a light.
+pub struct LightSample {
+    /// Unit vector from the point to the light.
+    pub dir: Vec3,
+    /// Distance to the light along `dir`.
+    pub dist: f32,
+    /// Probability density of the direction, per unit solid angle.
+    pub pdf: f32,
+}
+
+/// Shape of an emitting object. Only its geometry is known: the emitted light is found by
+/// tracing a ray towards it, which also tells whether it is occluded.
+pub trait Light: Send + Sync {
+    /// Samples a direction from `p` towards the light, `None` if none can be found.
+    fn sample(&self, p: Vec3, rng: &mut SampleRng) -> Option<LightSample>;
+
+    /// Distance at which `ray` hits the light, if it does, and probability density with which
+    /// [`sample`](Light::sample) picks its direction from its origin.
+    fn hit_pdf(&self, ray: &Ray) -> Option<(f32, f32)>;
+}
+
+/// Spherical light, sampled uniformly in the cone it covers as seen from the point.
+pub struct SphereLight {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl SphereLight {
+    /// Density of the directions seen from `p`, uniform in the cone covering the sphere or in
+    /// every direction from inside it.
+    fn pdf(&self, p: Vec3) -> f32 {
+        let r2 = self.radius * self.radius;
+        let dist2 = (self.center - p).mag_sq();
+        if dist2 <= r2 {
+            return 1.0 / (4.0 * PI);
+        }
+        // 1 - cos(theta_max), rearranged to stay accurate for small and distant spheres.
+        let sin2 = r2 / dist2;
+        let one_minus_cos = sin2 / (1.0 + (1.0 - sin2).sqrt());
+        1.0 / (2.0 * PI * one_minus_cos)
+    }
+
+    fn distance(&self, pos: Vec3, dir: Vec3) -> Option<f32> {
+        let oc = pos - self.center;
+        let half_b = oc.dot(dir);
+        let c = oc.mag_sq() - self.radius * self.radius;
+        let discriminant = half_b * half_b - c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        [-half_b - root, -half_b + root]
+            .iter()
+            .copied()
+            .find(|&t| t > 0.0)
+    }
+}
+
+impl Light for SphereLight {
+    fn sample(&self, p: Vec3, rng: &mut SampleRng) -> Option<LightSample> {
+        let to_center = self.center - p;
+        let dist2 = to_center.mag_sq();
+        let r2 = self.radius * self.radius;
+
+        let dir = if dist2 <= r2 {
+            random_unit_vector(rng)
+        } else {
+            let sin2 = r2 / dist2;
+            let one_minus_cos_max = sin2 / (1.0 + (1.0 - sin2).sqrt());
+            let one_minus_cos = rng.gen::<f32>() * one_minus_cos_max;
+            let cos = 1.0 - one_minus_cos;
+            let sin = (one_minus_cos * (2.0 - one_minus_cos)).max(0.0).sqrt();
+            let phi = 2.0 * PI * rng.gen::<f32>();
+
+            let w = to_center / dist2.sqrt();
+            let (u, v) = orthonormal_basis(w);
+            (u * (phi.cos() * sin) + v * (phi.sin() * sin) + w * cos).normalized()
+        };
+
+        // Directions grazing the sphere may miss it because of rounding.
+        let dist = self.distance(p, dir)?;
+        Some(LightSample {
+            dir,
+            dist,
+            pdf: self.pdf(p),
+        })
+    }
+
+    fn hit_pdf(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let t = self.distance(ray.pos, ray.dir)?;
+        Some((t, self.pdf(ray.pos)))
+    }
+}
+
+/// Parallelogram light, sampled uniformly over its area. It emits from both sides.
+pub struct QuadLight(pub(crate) Parallelogram);
+
+impl QuadLight {
+    /// Converts the density per unit area to a density per solid angle seen from `dist` away.
+    fn pdf(&self, dir: Vec3, dist: f32) -> Option<f32> {
+        let cos = self.0.normal().dot(dir).abs();
+        if cos < 1e-6 {
+            return None;
+        }
+        Some(dist * dist / (cos * self.0.area()))
+    }
+}
+
+impl Light for QuadLight {
+    fn sample(&self, p: Vec3, rng: &mut SampleRng) -> Option<LightSample> {
+        let q = self.0.at(Vec2::new(rng.gen(), rng.gen()));
+        let to_light = q - p;
+        let dist = to_light.mag();
+        let dir = to_light / dist;
+        Some(LightSample {
+            dir,
+            dist,
+            pdf: self.pdf(dir, dist)?,
+        })
+    }
+
+    fn hit_pdf(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let (t, _) = self.0.intersect(ray, 0.0, f32::INFINITY)?;
+        Some((t, self.pdf(ray.dir, t)?))
+    }
+}
@@ -1,24 +1,17 @@
-mod aabb;
-mod bvh;
-mod camera;
-mod hittable;
-mod image_gen;
-mod material;
-mod ray;
-mod raytrace;
+mod headless;
 mod render;
-mod utils;
-
-use crate::bvh::BVH;
-use crate::hittable::sphere::Sphere;
-use crate::hittable::Hittable;
-use crate::image_gen::ImageProvider;
-use crate::material::{Dielectric, Lambertian, Metal};
-use crate::raytrace::{vec3, RayTracer};
+
+use crate::headless::HeadlessOptions;
 use crate::render::Renderer;
 use miniquad::*;
+use raytracing::hittable::Hittable;
+use raytracing::image_gen::ImageProvider;
+use raytracing::integrator;
+use raytracing::output;
+use raytracing::raytrace::RayTracer;
+use raytracing::scenes;
+use raytracing::scenes::random_scene;
 use std::time::Instant;
-use ultraviolet::Vec3;
 
 struct Stage {
     renderer: Renderer,
@@ -27,85 +20,12 @@ struct Stage {
     last: Option<(f32, f32)>,
 }
 
-fn random_scene() -> impl Hittable {
-    let mut objects: Vec<Box<dyn Hittable>> = vec![];
-
-    let ground = Box::new(Sphere {
-        center: vec3(0.0, -1000.0, 0.0),
-        radius: 1000.0,
-        mat: Lambertian::new(Vec3::broadcast(0.5)),
-    });
-
-    for a in -5..5 {
-        for b in -5..5 {
-            let choose_mat = rand::random::<f32>();
-            let center = vec3(
-                a as f32 + 0.9 * rand::random::<f32>(),
-                0.2,
-                b as f32 + 0.9 * rand::random::<f32>(),
-            );
-
-            if (center - vec3(4.0, 0.2, 0.0)).mag() > 0.9 {
-                if choose_mat < 0.8 {
-                    // diffuse
-                    let albedo = Vec3::from(rand::random::<[f32; 3]>())
-                        * Vec3::from(rand::random::<[f32; 3]>());
-                    objects.push(Box::new(Sphere {
-                        center,
-                        radius: 0.2,
-                        mat: Lambertian::new(albedo),
-                    }));
-                } else if choose_mat < 0.95 {
-                    // metal
-                    let albedo =
-                        Vec3::broadcast(0.5) + Vec3::from(rand::random::<[f32; 3]>()) * 0.5;
-                    let fuzz = rand::random::<f32>() * 0.5;
-                    objects.push(Box::new(Sphere {
-                        center,
-                        radius: 0.2,
-                        mat: Metal::new(albedo, fuzz),
-                    }));
-                } else {
-                    // glass
-                    objects.push(Box::new(Sphere {
-                        center,
-                        radius: 0.2,
-                        mat: Dielectric::new(1.5),
-                    }));
-                }
-            }
-        }
-    }
-
-    objects.push(Box::new(Sphere {
-        center: vec3(0.0, 1.0, 0.0),
-        radius: 1.0,
-        mat: Dielectric::new(1.5),
-    }));
-
-    objects.push(Box::new(Sphere {
-        center: vec3(-4.0, 1.0, 0.0),
-        radius: 1.0,
-        mat: Lambertian::new(vec3(0.4, 0.2, 0.1)),
-    }));
-
-    objects.push(Box::new(Sphere {
-        center: vec3(4.0, 1.0, 0.0),
-        radius: 1.0,
-        mat: Metal::new(vec3(0.7, 0.6, 0.5), 0.0),
-    }));
-
-    return vec![ground as Box<dyn Hittable>, Box::new(BVH::new(objects))];
-}
-
 impl Stage {
-    pub fn new(ctx: &mut Context) -> Stage {
-        let world = random_scene();
-
+    pub fn new(ctx: &mut Context, tracer: RayTracer<Box<dyn Hittable>>) -> Stage {
         Stage {
             renderer: Renderer::new(ctx),
             provider: ImageProvider::new(),
-            tracer: RayTracer::new(Box::new(world)),
+            tracer,
             last: None,
         }
     }
@@ -121,22 +41,36 @@ impl EventHandler for Stage {
         let pixels = self
             .provider
             .get_next(&mut self.tracer, w as usize, h as usize);
-        print!("Image gen took {}ms\n", t.elapsed().as_secs_f32() * 1000.0);
+        println!("Image gen took {}ms", t.elapsed().as_secs_f32() * 1000.0);
 
         self.renderer.draw_pixels(ctx, pixels);
     }
+
+    fn resize_event(&mut self, _: &mut Context, width: f32, height: f32) {
+        println!("\nresized to {} {}", width, height);
+    }
     fn mouse_motion_event(&mut self, _: &mut Context, x: f32, y: f32) {
         if let Some((lx, ly)) = self.last {
-            self.tracer.cam.eye_horiz(0.003 * (x - lx) as f32);
+            self.tracer.cam.eye_horiz(0.003 * (x - lx));
             self.tracer
                 .cam
-                .eye_vert(0.003 * (y - ly) as f32 * self.tracer.cam.aspect_ratio);
+                .eye_vert(0.003 * (y - ly) * self.tracer.cam.aspect_ratio);
             self.provider.moved();
             self.last = Some((x, y));
         }
     }
 
-    fn mouse_button_down_event(&mut self, _: &mut Context, _: MouseButton, x: f32, y: f32) {
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if button == MouseButton::Right {
+            // Rows of the image go from bottom to top.
+            let (w, h) = ctx.screen_size();
+            if let Some(d) = self.tracer.focus_distance(x / w, 1.0 - y / h) {
+                self.tracer.cam.focus_dist = d;
+                self.provider.moved();
+                println!("\nfocus distance: {:.2}", d);
+            }
+            return;
+        }
         self.last = Some((x, y));
     }
 
@@ -177,6 +111,88 @@ impl EventHandler for Stage {
                 self.tracer.cam.up(-SPEED);
                 self.provider.moved();
             }
+            KeyCode::T => {
+                let tm = &mut self.provider.tone_mapper;
+                tm.operator = tm.operator.next();
+                println!("\ntone mapping: {}", tm.operator);
+            }
+            KeyCode::Equal | KeyCode::KpAdd => {
+                self.provider.tone_mapper.exposure += 0.5;
+                println!("\nexposure: {:+} EV", self.provider.tone_mapper.exposure);
+            }
+            KeyCode::Minus | KeyCode::KpSubtract => {
+                self.provider.tone_mapper.exposure -= 0.5;
+                println!("\nexposure: {:+} EV", self.provider.tone_mapper.exposure);
+            }
+            KeyCode::LeftBracket | KeyCode::RightBracket => {
+                let cam = &mut self.tracer.cam;
+                cam.aperture = if keycode == KeyCode::RightBracket {
+                    cam.aperture + 0.05
+                } else {
+                    (cam.aperture - 0.05).max(0.0)
+                };
+                self.provider.moved();
+                println!("\naperture: {:.2}", cam.aperture);
+            }
+            KeyCode::Comma | KeyCode::Period => {
+                let cam = &mut self.tracer.cam;
+                cam.focus_dist *= if keycode == KeyCode::Period {
+                    1.1
+                } else {
+                    1.0 / 1.1
+                };
+                self.provider.moved();
+                println!("\nfocus distance: {:.2}", cam.focus_dist);
+            }
+            KeyCode::B => {
+                let cam = &mut self.tracer.cam;
+                cam.blades = match cam.blades {
+                    0 => 5,
+                    5 => 6,
+                    6 => 8,
+                    _ => 0,
+                };
+                self.provider.moved();
+                println!("\naperture blades: {}", cam.blades);
+            }
+            KeyCode::M => {
+                let cam = &mut self.tracer.cam;
+                cam.shutter_close = if cam.shutter_close > cam.shutter_open {
+                    cam.shutter_open
+                } else {
+                    cam.shutter_open + 1.0
+                };
+                self.provider.moved();
+                println!(
+                    "\nmotion blur: {}",
+                    if cam.shutter_close > cam.shutter_open {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                );
+            }
+            KeyCode::I => {
+                let names = &integrator::NAMES;
+                let current = self.tracer.integrator.name();
+                let i = names
+                    .iter()
+                    .position(|&x| x == current)
+                    .map_or(0, |i| i + 1);
+                let name = names[i % names.len()];
+                self.tracer.integrator =
+                    integrator::from_name(name, self.tracer.cam.focus_dist).unwrap();
+                self.provider.moved();
+                println!("\nintegrator: {}", name);
+            }
+            KeyCode::P => {
+                for path in &["screenshot.png", "screenshot.pfm"] {
+                    match output::save(&self.provider, path) {
+                        Ok(()) => println!("\nsaved {}", path),
+                        Err(e) => eprintln!("\ncould not write {}: {}", path, e),
+                    }
+                }
+            }
             _ => {}
         }
     }
@@ -184,11 +200,94 @@ impl EventHandler for Stage {
     fn key_up_event(&mut self, _ctx: &mut Context, _keycode: KeyCode, _keymods: KeyMods) {}
 }
 
+/// Removes `flag` and its value from the arguments.
+fn take_arg(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let i = args.iter().position(|x| x == flag)?;
+    if i + 1 >= args.len() {
+        eprintln!("missing value for {}", flag);
+        std::process::exit(2);
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Some(value)
+}
+
+/// Handles `--seed N`, `--depth N`, `--integrator NAME` and `--scene FILE`, the random scene is
+/// used if no file is given.
+fn load_tracer(args: &mut Vec<String>) -> RayTracer<Box<dyn Hittable>> {
+    let seed = match take_arg(args, "--seed").map(|x| x.parse()) {
+        None => 0,
+        Some(Ok(x)) => x,
+        Some(Err(_)) => {
+            eprintln!("invalid value for --seed");
+            std::process::exit(2);
+        }
+    };
+
+    let mut tracer = match take_arg(args, "--scene") {
+        None => RayTracer::new(Box::new(random_scene(seed)) as Box<dyn Hittable>),
+        Some(path) => match scenes::file::load(&path) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("could not load scene {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+    };
+    tracer.seed = seed;
+    if let Some(depth) = take_arg(args, "--depth") {
+        tracer.max_depth = match depth.parse() {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("invalid value for --depth: {}", e);
+                std::process::exit(2);
+            }
+        };
+    }
+    if let Some(name) = take_arg(args, "--integrator") {
+        tracer.integrator = match integrator::from_name(&name, tracer.cam.focus_dist) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("invalid value for --integrator: {}", e);
+                std::process::exit(2);
+            }
+        };
+    }
+    tracer
+}
+
+fn run_headless(args: &[String], mut tracer: RayTracer<Box<dyn Hittable>>) {
+    let opts = match HeadlessOptions::parse(args) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{}\n{}", e, headless::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(e) = headless::render(&mut tracer, &opts) {
+        eprintln!("could not write {}: {}", opts.output, e);
+        std::process::exit(1);
+    }
+}
+
 fn main() {
-    let mut conf = conf::Conf::default();
-    conf.window_title = "raytrace".to_owned();
-    conf.window_width = 1280 / 4;
-    conf.window_height = 720 / 4;
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let tracer = load_tracer(&mut args);
 
-    miniquad::start(conf, |mut ctx| UserData::owning(Stage::new(&mut ctx), ctx));
+    if args.iter().any(|x| x == "--headless") {
+        run_headless(&args, tracer);
+        return;
+    }
+
+    let conf = conf::Conf {
+        window_title: "raytrace".to_owned(),
+        window_width: 1280 / 4,
+        window_height: 720 / 4,
+        ..Default::default()
+    };
+
+    miniquad::start(conf, |mut ctx| {
+        UserData::owning(Stage::new(&mut ctx, tracer), ctx)
+    });
 }
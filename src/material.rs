@@ -1,67 +1,192 @@
 use crate::hittable::Hit;
 use crate::ray::Ray;
-use crate::utils::{random_in_sphere, random_unit_vector};
+use crate::raytrace::Color;
+use crate::texture::Texture;
+use crate::utils::{random_in_sphere, random_unit_vector, SampleRng};
+use rand::Rng;
+use std::f32::consts::PI;
+use std::ops::Deref;
+use std::sync::Arc;
 use ultraviolet::Vec3;
 
+/// How a ray was scattered, paths have a depth limit for each kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScatterKind {
+    /// In a spread of directions described by [`Material::eval`] and [`Material::pdf`]: diffuse
+    /// and glossy reflection, or scattering in a volume. Lights are sampled at these bounces.
+    Diffuse,
+    /// Reflection in a single direction, like by a mirror.
+    Specular,
+    /// Refraction through the surface in a single direction, like by glass.
+    Transmission,
+}
+
+/// A ray bounced off a surface and the color it is filtered by.
 pub struct ScatterResult {
     pub scattered: Ray,
     pub attenuation: Vec3,
+    pub kind: ScatterKind,
 }
 
+/// Describes how light interacts with a surface.
 pub trait Material: Send + Sync {
-    fn scatter<'a>(&self, ray: &Ray, hit: Hit<'a>) -> Option<ScatterResult>;
+    /// Returns the scattered ray, or `None` if the ray is absorbed.
+    fn scatter<'a>(&self, ray: &Ray, hit: Hit<'a>, rng: &mut SampleRng) -> Option<ScatterResult>;
+
+    /// Light emitted by the surface towards the ray, black for everything but light sources.
+    fn emitted(&self, _ray: &Ray, _hit: &Hit<'_>) -> Color {
+        Color::zero()
+    }
+
+    /// Whether [`emitted`](Material::emitted) can be anything but black.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Fraction of the light arriving from the direction `dir` that is scattered back along
+    /// `ray`: the BSDF times the cosine of the angle between `dir` and the normal. Used to
+    /// sample lights, it is black unless scattering is [`ScatterKind::Diffuse`].
+    fn eval(&self, _ray: &Ray, _hit: &Hit<'_>, _dir: Vec3) -> Color {
+        Color::zero()
+    }
+
+    /// Probability density, per unit solid angle, of [`scatter`](Material::scatter) choosing
+    /// the direction `dir`. Zero unless scattering is [`ScatterKind::Diffuse`].
+    fn pdf(&self, _ray: &Ray, _hit: &Hit<'_>, _dir: Vec3) -> f32 {
+        0.0
+    }
+
+    /// Identifies the material, shared materials have the same id. The address by default.
+    fn id(&self) -> usize {
+        self as *const Self as *const () as usize
+    }
 }
 
-pub struct Lambertian {
-    pub albedo: Vec3,
+impl Material for Arc<dyn Material> {
+    fn scatter<'a>(&self, ray: &Ray, hit: Hit<'a>, rng: &mut SampleRng) -> Option<ScatterResult> {
+        self.deref().scatter(ray, hit, rng)
+    }
+
+    fn emitted(&self, ray: &Ray, hit: &Hit<'_>) -> Color {
+        self.deref().emitted(ray, hit)
+    }
+
+    fn is_emissive(&self) -> bool {
+        self.deref().is_emissive()
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit<'_>, dir: Vec3) -> Color {
+        self.deref().eval(ray, hit, dir)
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &Hit<'_>, dir: Vec3) -> f32 {
+        self.deref().pdf(ray, hit, dir)
+    }
+
+    fn id(&self) -> usize {
+        self.deref().id()
+    }
+}
+
+/// Ideal diffuse surface.
+pub struct Lambertian<T: Texture = Color> {
+    pub albedo: T,
 }
 
-impl Lambertian {
-    pub fn new(albedo: Vec3) -> Self {
+impl<T: Texture> Lambertian<T> {
+    pub fn new(albedo: T) -> Self {
         Self { albedo }
     }
 }
 
-impl Material for Lambertian {
-    fn scatter<'a>(&self, _ray: &Ray, hit: Hit<'a>) -> Option<ScatterResult> {
-        let bounce_dir = (hit.normal + random_unit_vector()).normalized();
+impl<T: Texture> Material for Lambertian<T> {
+    fn scatter<'a>(&self, ray: &Ray, hit: Hit<'a>, rng: &mut SampleRng) -> Option<ScatterResult> {
+        let bounce_dir = (hit.normal + random_unit_vector(rng)).normalized();
 
         Some(ScatterResult {
-            scattered: Ray::new(hit.p, bounce_dir),
-            attenuation: self.albedo,
+            scattered: Ray::with_time(hit.p, bounce_dir, ray.time),
+            attenuation: self.albedo.value(hit.uv, hit.p),
+            kind: ScatterKind::Diffuse,
         })
     }
+
+    fn eval(&self, ray: &Ray, hit: &Hit<'_>, dir: Vec3) -> Color {
+        self.albedo.value(hit.uv, hit.p) * self.pdf(ray, hit, dir)
+    }
+
+    fn pdf(&self, _ray: &Ray, hit: &Hit<'_>, dir: Vec3) -> f32 {
+        hit.normal.dot(dir).max(0.0) / PI
+    }
 }
 
-pub struct Metal {
-    pub albedo: Vec3,
+/// Reflective surface, `fuzz` randomizes the reflected direction: it is offset by a random
+/// vector shorter than `fuzz`.
+pub struct Metal<T: Texture = Color> {
+    pub albedo: T,
     pub fuzz: f32,
 }
 
-impl Metal {
-    pub fn new(albedo: Vec3, fuzz: f32) -> Self {
+impl<T: Texture> Metal<T> {
+    pub fn new(albedo: T, fuzz: f32) -> Self {
         Self { albedo, fuzz }
     }
 }
 
-impl Material for Metal {
-    fn scatter<'a>(&self, ray: &Ray, hit: Hit<'a>) -> Option<ScatterResult> {
+impl<T: Texture> Material for Metal<T> {
+    fn scatter<'a>(&self, ray: &Ray, hit: Hit<'a>, rng: &mut SampleRng) -> Option<ScatterResult> {
         let reflected = ray.dir.reflected(hit.normal);
 
         if reflected.dot(hit.normal) > 0.0 {
             Some(ScatterResult {
-                scattered: Ray::new(
+                scattered: Ray::with_time(
                     hit.p,
-                    (reflected + self.fuzz * random_in_sphere()).normalized(),
+                    (reflected + self.fuzz * random_in_sphere(rng)).normalized(),
+                    ray.time,
                 ),
-                attenuation: self.albedo,
+                attenuation: self.albedo.value(hit.uv, hit.p),
+                kind: if self.fuzz > 0.0 {
+                    ScatterKind::Diffuse
+                } else {
+                    ScatterKind::Specular
+                },
             })
         } else {
             None
         }
     }
+
+    fn eval(&self, ray: &Ray, hit: &Hit<'_>, dir: Vec3) -> Color {
+        if hit.normal.dot(dir) <= 0.0 {
+            return Color::zero();
+        }
+        self.albedo.value(hit.uv, hit.p) * self.pdf(ray, hit, dir)
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &Hit<'_>, dir: Vec3) -> f32 {
+        if self.fuzz <= 0.0 {
+            return 0.0;
+        }
+
+        // The direction is chosen by a point uniformly distributed in the ball of radius fuzz
+        // around the reflected direction, so its density is the part of the ball on the half
+        // line along `dir`, from `s1` to `s2`, integrated in spherical coordinates.
+        let cos = ray.dir.reflected(hit.normal).dot(dir);
+        let f2 = self.fuzz * self.fuzz;
+        let h2 = f2 - (1.0 - cos * cos);
+        if h2 < 0.0 {
+            return 0.0;
+        }
+        let h = h2.sqrt();
+        let s2 = cos + h;
+        if s2 <= 0.0 {
+            return 0.0;
+        }
+        let s1 = (cos - h).max(0.0);
+        (s2 * s2 * s2 - s1 * s1 * s1) / (4.0 * PI * f2 * self.fuzz)
+    }
 }
 
+/// Refractive material such as glass, `ref_idx` being its refractive index.
 pub struct Dielectric {
     pub ref_idx: f32,
 }
@@ -76,17 +201,17 @@ fn refract(uv: Vec3, n: Vec3, etai_over_etat: f32) -> Vec3 {
     let cos_theta = -uv.dot(n);
     let r_out_parallel = etai_over_etat * (uv + cos_theta * n);
     let r_out_perp = -(1.0 - r_out_parallel.mag_sq()).sqrt() * n;
-    return r_out_parallel + r_out_perp;
+    r_out_parallel + r_out_perp
 }
 
 fn schlick(cosine: f32, ref_idx: f32) -> f32 {
     let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
     let r0 = r0 * r0;
-    return r0 + (1.0 - r0) * f32::powi(1.0 - cosine, 5);
+    r0 + (1.0 - r0) * f32::powi(1.0 - cosine, 5)
 }
 
 impl Material for Dielectric {
-    fn scatter<'a>(&self, ray: &Ray, hit: Hit<'a>) -> Option<ScatterResult> {
+    fn scatter<'a>(&self, ray: &Ray, hit: Hit<'a>, rng: &mut SampleRng) -> Option<ScatterResult> {
         let eta = if hit.front_face {
             1.0 / self.ref_idx
         } else {
@@ -98,25 +223,94 @@ impl Material for Dielectric {
         if eta * sin_theta > 1.0 {
             let reflected = ray.dir.reflected(hit.normal);
             return Some(ScatterResult {
-                scattered: Ray::new(hit.p, reflected),
+                scattered: Ray::with_time(hit.p, reflected, ray.time),
                 attenuation: Vec3::one(),
+                kind: ScatterKind::Specular,
             });
         }
 
         let reflect_prob = schlick(cos_theta, self.ref_idx);
-        if rand::random::<f32>() < reflect_prob {
+        if rng.gen::<f32>() < reflect_prob {
             let reflected = ray.dir.reflected(hit.normal);
             return Some(ScatterResult {
-                scattered: Ray::new(hit.p, reflected),
+                scattered: Ray::with_time(hit.p, reflected, ray.time),
                 attenuation: Vec3::one(),
+                kind: ScatterKind::Specular,
             });
         }
 
         let refracted = refract(ray.dir, hit.normal, eta);
 
         Some(ScatterResult {
-            scattered: Ray::new(hit.p, refracted),
+            scattered: Ray::with_time(hit.p, refracted, ray.time),
             attenuation: Vec3::one(),
+            kind: ScatterKind::Transmission,
+        })
+    }
+}
+
+/// Emits light uniformly in every direction and absorbs incoming rays, turning any [`Hittable`]
+/// into an area light.
+///
+/// [`Hittable`]: crate::hittable::Hittable
+pub struct DiffuseLight<T: Texture = Color> {
+    /// Emitted radiance, usually brighter than 1.
+    pub emit: T,
+}
+
+impl<T: Texture> DiffuseLight<T> {
+    pub fn new(emit: T) -> Self {
+        Self { emit }
+    }
+}
+
+impl<T: Texture> Material for DiffuseLight<T> {
+    fn scatter<'a>(
+        &self,
+        _ray: &Ray,
+        _hit: Hit<'a>,
+        _rng: &mut SampleRng,
+    ) -> Option<ScatterResult> {
+        None
+    }
+
+    fn emitted(&self, _ray: &Ray, hit: &Hit<'_>) -> Color {
+        self.emit.value(hit.uv, hit.p)
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
+
+/// Phase function scattering light equally in every direction, for participating media such as
+/// [`ConstantMedium`].
+///
+/// [`ConstantMedium`]: crate::hittable::medium::ConstantMedium
+pub struct Isotropic<T: Texture = Color> {
+    pub albedo: T,
+}
+
+impl<T: Texture> Isotropic<T> {
+    pub fn new(albedo: T) -> Self {
+        Self { albedo }
+    }
+}
+
+impl<T: Texture> Material for Isotropic<T> {
+    fn scatter<'a>(&self, ray: &Ray, hit: Hit<'a>, rng: &mut SampleRng) -> Option<ScatterResult> {
+        Some(ScatterResult {
+            scattered: Ray::with_time(hit.p, random_unit_vector(rng), ray.time),
+            attenuation: self.albedo.value(hit.uv, hit.p),
+            kind: ScatterKind::Diffuse,
         })
     }
+
+    fn eval(&self, ray: &Ray, hit: &Hit<'_>, dir: Vec3) -> Color {
+        self.albedo.value(hit.uv, hit.p) * self.pdf(ray, hit, dir)
+    }
+
+    fn pdf(&self, _ray: &Ray, _hit: &Hit<'_>, _dir: Vec3) -> f32 {
+        1.0 / (4.0 * PI)
+    }
 }
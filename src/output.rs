@@ -0,0 +1,91 @@
+//! Writers for rendered images.
+//!
+//! Images are given as produced by [`ImageProvider`]: rows from bottom to top, either RGBA8
+//! after tone mapping or linear [`Color`]s.
+
+use crate::image_gen::ImageProvider;
+use crate::raytrace::Color;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// 8-bit PNG, tone mapped.
+    PNG,
+    /// 8-bit binary PPM, tone mapped.
+    PPM,
+    /// Portable float map, linear and unclamped.
+    PFM,
+}
+
+impl ImageFormat {
+    /// Guesses the format from the extension of `path`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "png" => Some(ImageFormat::PNG),
+            "ppm" => Some(ImageFormat::PPM),
+            "pfm" => Some(ImageFormat::PFM),
+            _ => None,
+        }
+    }
+}
+
+fn flipped_rows(pixels: &[u8], width: usize) -> impl Iterator<Item = &[u8]> {
+    pixels.chunks_exact(width * 4).rev()
+}
+
+pub fn write_png(path: &Path, width: usize, height: usize, pixels: &[u8]) -> std::io::Result<()> {
+    let f = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(f, width as u32, height as u32);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let data: Vec<u8> = flipped_rows(pixels, width).flatten().copied().collect();
+    encoder.write_header()?.write_image_data(&data)?;
+    Ok(())
+}
+
+pub fn write_ppm(path: &Path, width: usize, height: usize, pixels: &[u8]) -> std::io::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    write!(f, "P6\n{} {}\n255\n", width, height)?;
+    for line in flipped_rows(pixels, width) {
+        for rgba in line.chunks_exact(4) {
+            f.write_all(&rgba[..3])?;
+        }
+    }
+    f.flush()
+}
+
+/// PFM stores rows from bottom to top already, and a negative scale means little endian.
+pub fn write_pfm(
+    path: &Path,
+    width: usize,
+    height: usize,
+    pixels: &[Color],
+) -> std::io::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    write!(f, "PF\n{} {}\n-1.0\n", width, height)?;
+    for c in pixels {
+        for v in &[c.x, c.y, c.z] {
+            f.write_all(&v.to_le_bytes())?;
+        }
+    }
+    f.flush()
+}
+
+/// Saves the current image of `provider`, in the format given by the extension of `path`.
+pub fn save(provider: &ImageProvider, path: impl AsRef<Path>) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let (w, h) = (provider.width(), provider.height());
+    match ImageFormat::from_path(path) {
+        Some(ImageFormat::PNG) => write_png(path, w, h, provider.pixels()),
+        Some(ImageFormat::PPM) => write_ppm(path, w, h, provider.pixels()),
+        Some(ImageFormat::PFM) => write_pfm(path, w, h, &provider.linear()),
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "unknown image format, expected .png, .ppm or .pfm",
+        )),
+    }
+}
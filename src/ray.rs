@@ -1,14 +1,22 @@
 use ultraviolet::Vec3;
 
+/// A half-line starting at `pos`, `dir` is normalized.
 pub struct Ray {
     pub pos: Vec3,
     pub dir: Vec3,
+    /// Instant at which the ray is traced, for moving objects.
+    pub time: f32,
 }
 
 impl Ray {
     pub fn new(pos: Vec3, dir: Vec3) -> Self {
-        Self { pos, dir }
+        Self::with_time(pos, dir, 0.0)
     }
+
+    pub fn with_time(pos: Vec3, dir: Vec3, time: f32) -> Self {
+        Self { pos, dir, time }
+    }
+
     pub fn at(&self, t: f32) -> Vec3 {
         self.pos + self.dir * t
     }
@@ -1,57 +1,116 @@
 use crate::camera::{Camera, RayGenerator};
 use crate::hittable::Hittable;
-use crate::material::ScatterResult;
+use crate::integrator::{Integrator, MaxDepth, PathTracer, Scene};
+use crate::light::Light;
 use crate::ray::Ray;
+use crate::utils::sample_rng;
+use rand::Rng;
+use std::sync::Arc;
 use ultraviolet::Vec3;
 
 pub fn vec3(x: f32, y: f32, z: f32) -> Vec3 {
     Vec3 { x, y, z }
 }
 
-type Color = Vec3;
+pub type Color = Vec3;
 
+/// What rays that escape the scene see.
+pub enum Background {
+    /// Vertical gradient from white to light blue.
+    Sky,
+    Solid(Color),
+}
+
+impl Background {
+    pub fn color(&self, ray: &Ray) -> Color {
+        match *self {
+            Background::Sky => {
+                let v = 0.5 * ray.dir.y + 0.5;
+                (1.0 - v) * vec3(1.0, 1.0, 1.0) + v * vec3(0.5, 0.7, 1.0)
+            }
+            Background::Solid(c) => c,
+        }
+    }
+}
+
+/// Owns the scene and the camera, and computes the color of individual samples.
 pub struct RayTracer<T: Hittable + Sync> {
     pub cam: Camera,
+    pub background: Background,
+    /// Seed of all the random numbers used for sampling.
+    pub seed: u64,
+    pub max_depth: MaxDepth,
+    /// Computes the color of the samples, a [`PathTracer`] by default.
+    pub integrator: Arc<dyn Integrator>,
     gen: RayGenerator,
     world: T,
+    /// Lights sampled at every diffuse bounce, collected from the world.
+    lights: Vec<Box<dyn Light>>,
 }
 
 impl<T: Hittable + Sync> RayTracer<T> {
     pub fn new(world: T) -> Self {
         let cam = Camera::default();
         let gen = cam.ray_generator();
-        Self { world, cam, gen }
+        let mut lights = vec![];
+        world.lights(&mut lights);
+        Self {
+            world,
+            cam,
+            background: Background::Sky,
+            seed: 0,
+            max_depth: MaxDepth::default(),
+            integrator: Arc::new(PathTracer),
+            gen,
+            lights,
+        }
     }
 
-    fn ray_color(&self, ray: &Ray, depth: u32) -> Color {
-        if depth > 0 {
-            if let Some(hit) = self.world.hit(ray, 0.001, std::f32::INFINITY) {
-                if let Some(ScatterResult {
-                    scattered,
-                    attenuation,
-                }) = hit.mat.scatter(ray, hit)
-                {
-                    return attenuation * self.ray_color(&scattered, depth - 1);
-                }
-                return Vec3::zero();
-            }
-        } else {
-            return Vec3::zero();
-        }
+    /// Number of lights sampled explicitly.
+    pub fn light_count(&self) -> usize {
+        self.lights.len()
+    }
 
-        let v = 0.5 * ray.dir.y + 0.5;
-        (1.0 - v) * vec3(1.0, 1.0, 1.0) + v * vec3(0.5, 0.7, 1.0)
+    /// The scene as seen by the [`integrator`](RayTracer::integrator).
+    pub fn scene(&self) -> Scene<'_> {
+        Scene {
+            world: &self.world,
+            lights: &self.lights,
+            background: &self.background,
+            max_depth: self.max_depth,
+        }
     }
 
+    /// Recomputes the ray generator, must be called after modifying [`cam`](RayTracer::cam).
     pub fn init(&mut self) {
         self.gen = self.cam.ray_generator();
     }
 
-    pub fn get_sample(&self, x: f32, y: f32, resx: f32, resy: f32) -> Color {
+    /// Distance from the camera to the closest object seen at the point `(u, v)` of the
+    /// viewport, measured along the view direction like [`Camera::focus_dist`].
+    pub fn focus_distance(&self, u: f32, v: f32) -> Option<f32> {
+        let ray = self.gen.center_ray(u, v);
+        let hit = self.world.hit(&ray, 0.001, f32::INFINITY)?;
+        Some(hit.t * -ray.dir.dot(self.cam.eye))
+    }
+
+    /// Traces one ray through a random point of pixel `(x, y)` of a `width` by `height` image,
+    /// counting from the lower left corner. The random numbers only depend on the pixel, the
+    /// `sample` index and the [`seed`](RayTracer::seed), so renders are reproducible.
+    pub fn get_sample(
+        &self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        sample: u32,
+    ) -> Color {
+        let mut rng = sample_rng(self.seed, x as u32, y as u32, sample);
         let ray = self.gen.ray(
-            x + resx * rand::random::<f32>(),
-            y + resy * rand::random::<f32>(),
+            (x as f32 + rng.gen::<f32>()) / width as f32,
+            (y as f32 + rng.gen::<f32>()) / height as f32,
+            &mut rng,
         );
-        self.ray_color(&ray, 4)
+        self.integrator.radiance(&self.scene(), ray, &mut rng)
     }
 }
@@ -59,7 +59,7 @@ impl Renderer {
         let w = w as u16;
         let h = h as u16;
 
-        let texture = Texture::from_rgba8(ctx, w, h, &pixels);
+        let texture = Texture::from_rgba8(ctx, w, h, pixels);
 
         let bindings = Bindings {
             vertex_buffers: vec![self.vertex_buffer],
@@ -0,0 +1,833 @@
+//! Loader for scenes described in TOML.
+//!
+//! ```toml
+//! background = "sky"          # or an RGB color such as [0.0, 0.0, 0.0]
+//!
+//! [camera]
+//! pos = [13.0, 2.0, 3.0]
+//! look_at = [0.0, 0.0, 0.0]
+//! up = [0.0, 1.0, 0.0]
+//! fov = 20.0
+//! aperture = 0.1              # lens diameter, 0 for a pinhole camera
+//! focus_dist = 10.0           # defaults to the distance to look_at
+//! blades = 6                  # polygonal bokeh, 0 for a round lens
+//! shutter = [0.0, 1.0]        # open and close times, for motion blur
+//!
+//! [textures.checker]
+//! type = "checker"            # "checker", "image" or "noise"
+//! even = [0.2, 0.3, 0.1]
+//! odd = [0.9, 0.9, 0.9]
+//! scale = 0.5                 # size of the squares
+//!
+//! [textures.earth]
+//! type = "image"
+//! file = "earth.jpg"          # PNG or JPEG, relative to the scene file
+//! wrap = "repeat"             # optional, "repeat", "clamp" or "mirror"
+//!
+//! [textures.marble]
+//! type = "noise"
+//! pattern = "marble"          # "noise", "turbulence", "marble", "wood" or "cellular"
+//! scale = 4.0                 # frequency of the noise
+//! octaves = 7                 # optional, number of layers of detail
+//! colors = [[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]]  # optional, blended by the pattern
+//! seed = 0                    # optional
+//!
+//! [materials.ground]
+//! type = "lambertian"         # "lambertian", "metal", "dielectric", "light" or "isotropic"
+//! albedo = "checker"          # a color or the name of a texture, also for metal, light and
+//!                             # isotropic
+//!
+//! [materials.glass]
+//! type = "dielectric"
+//! ref_idx = 1.5
+//!
+//! [materials.lamp]
+//! type = "light"
+//! emit = [4.0, 4.0, 4.0]      # radiance, can be above 1
+//!
+//! [[spheres]]
+//! center = [0.0, -1000.0, 0.0]
+//! radius = 1000.0
+//! material = "ground"
+//! center1 = [0.0, -999.0, 0.0] # optional, the sphere moves there between time 0 and 1
+//! density = 0.1               # optional, see below
+//!
+//! [[triangles]]
+//! vertices = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+//! normals = [[0.0, 0.0, 1.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0]]  # optional
+//! material = "glass"
+//!
+//! [[quads]]
+//! origin = [-1.0, 0.0, -1.0]  # a corner, the normal is u x v
+//! u = [2.0, 0.0, 0.0]
+//! v = [0.0, 0.0, 2.0]
+//! material = "ground"
+//!
+//! [[boxes]]
+//! min = [0.0, 0.0, 0.0]       # two opposite corners
+//! max = [1.0, 2.0, 1.0]
+//! material = "ground"
+//! rotate = [0.0, 15.0, 0.0]   # optional, see below
+//!
+//! [[meshes]]
+//! file = "bunny.obj"          # relative to the scene file
+//! material = "gold"           # optional, overrides the materials of the OBJ file
+//! scale = 2.0                 # optional, a factor or one per axis such as [1.0, 2.0, 1.0]
+//! rotate = [0.0, 90.0, 0.0]   # optional, degrees around the x, then y, then z axis
+//! translate = [0.0, 1.0, 0.0] # optional
+//!
+//! [[volumes]]
+//! grid = "smoke.vol"          # density grid in the VOL format of Mitsuba, or a procedural
+//!                             # cloud such as { min = [-1.0, 0.0, -1.0], max = [1.0, 1.0, 1.0],
+//!                             # scale = 2.0, resolution = 64, seed = 0 }
+//! absorption = 0.5            # coefficients multiplied by the density
+//! scattering = 4.0
+//! emission = [0.0, 0.0, 0.0]  # optional, radiance of the absorbing particles
+//! material = "smoke"          # optional, isotropic with a white albedo by default
+//! translate = [0.0, 1.0, 0.0] # optional, like boxes and meshes
+//! ```
+//!
+//! Every field of `[camera]` is optional and defaults to [`Camera::default`].
+//!
+//! Spheres and boxes with a `density` are not surfaces but volumes of fog or smoke filling
+//! them, and their material, usually `isotropic`, says how light scatters inside.
+//!
+//! Boxes, meshes and volumes can be transformed by `scale`, `rotate` and `translate`, applied in this
+//! order. A mesh file is only loaded once however many times it is used, the copies are
+//! instances sharing the same triangles.
+
+use crate::aabb::AABB;
+use crate::bvh::LinearBVH;
+use crate::camera::Camera;
+use crate::hittable::medium::{ConstantMedium, GridMedium};
+use crate::hittable::mesh::TriangleMesh;
+use crate::hittable::quad::{Cuboid, Quad};
+use crate::hittable::sphere::{MovingSphere, Sphere};
+use crate::hittable::transform::{Transform, Transformed};
+use crate::hittable::triangle::Triangle;
+use crate::hittable::Hittable;
+use crate::material::{Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal};
+use crate::raytrace::{Background, Color, RayTracer};
+use crate::scenes::obj;
+use crate::texture::noise::NoiseTexture;
+use crate::texture::{Checker, ImageTexture, Texture};
+use crate::volume::DensityGrid;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use toml::Spanned;
+use ultraviolet::Vec3;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    /// Syntax error or a field of the wrong type, the message contains the line.
+    Parse(toml::de::Error),
+    /// The file is well formed but describes an invalid scene.
+    Invalid {
+        line: usize,
+        msg: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "{}", e),
+            SceneError::Parse(e) => write!(f, "{}", e),
+            SceneError::Invalid { line, msg } => write!(f, "{} at line {}", msg, line),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(e: std::io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for SceneError {
+    fn from(e: toml::de::Error) -> Self {
+        SceneError::Parse(e)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDesc {
+    background: Option<Spanned<BackgroundDesc>>,
+    #[serde(default)]
+    camera: CameraDesc,
+    #[serde(default)]
+    textures: HashMap<String, TextureDesc>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDesc>,
+    #[serde(default)]
+    spheres: Vec<SphereDesc>,
+    #[serde(default)]
+    triangles: Vec<TriangleDesc>,
+    #[serde(default)]
+    quads: Vec<QuadDesc>,
+    #[serde(default)]
+    boxes: Vec<BoxDesc>,
+    #[serde(default)]
+    meshes: Vec<MeshDesc>,
+    #[serde(default)]
+    volumes: Vec<VolumeDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BackgroundDesc {
+    Named(String),
+    Color([f32; 3]),
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    pos: Option<[f32; 3]>,
+    look_at: Option<Spanned<[f32; 3]>>,
+    up: Option<Spanned<[f32; 3]>>,
+    fov: Option<Spanned<f32>>,
+    aperture: Option<Spanned<f32>>,
+    focus_dist: Option<Spanned<f32>>,
+    blades: Option<u32>,
+    shutter: Option<Spanned<[f32; 2]>>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum TextureDesc {
+    Checker {
+        even: [f32; 3],
+        odd: [f32; 3],
+        scale: f32,
+    },
+    Image {
+        file: String,
+        wrap: Option<String>,
+    },
+    Noise {
+        pattern: String,
+        scale: f32,
+        octaves: Option<u32>,
+        colors: Option<[[f32; 3]; 2]>,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorDesc {
+    Color([f32; 3]),
+    Texture(String),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian { albedo: ColorDesc },
+    Metal { albedo: ColorDesc, fuzz: f32 },
+    Dielectric { ref_idx: f32 },
+    Light { emit: ColorDesc },
+    Isotropic { albedo: ColorDesc },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereDesc {
+    center: [f32; 3],
+    center1: Option<[f32; 3]>,
+    radius: Spanned<f32>,
+    material: Spanned<String>,
+    density: Option<Spanned<f32>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriangleDesc {
+    vertices: Spanned<[[f32; 3]; 3]>,
+    normals: Option<Spanned<[[f32; 3]; 3]>>,
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QuadDesc {
+    origin: [f32; 3],
+    u: Spanned<[f32; 3]>,
+    v: Spanned<[f32; 3]>,
+    material: Spanned<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BoxDesc {
+    min: [f32; 3],
+    max: [f32; 3],
+    material: Spanned<String>,
+    density: Option<Spanned<f32>>,
+    scale: Option<Spanned<ScaleDesc>>,
+    rotate: Option<[f32; 3]>,
+    translate: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MeshDesc {
+    file: Spanned<String>,
+    material: Option<Spanned<String>>,
+    scale: Option<Spanned<ScaleDesc>>,
+    rotate: Option<[f32; 3]>,
+    translate: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VolumeDesc {
+    grid: Spanned<GridDesc>,
+    absorption: Spanned<f32>,
+    scattering: Spanned<f32>,
+    emission: Option<[f32; 3]>,
+    material: Option<Spanned<String>>,
+    scale: Option<Spanned<ScaleDesc>>,
+    rotate: Option<[f32; 3]>,
+    translate: Option<[f32; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GridDesc {
+    File(String),
+    Cloud {
+        min: [f32; 3],
+        max: [f32; 3],
+        scale: f32,
+        #[serde(default = "default_resolution")]
+        resolution: usize,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+fn default_resolution() -> usize {
+    64
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleDesc {
+    Uniform(f32),
+    PerAxis([f32; 3]),
+}
+
+struct Loader<'a> {
+    src: &'a str,
+    dir: &'a Path,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+    /// Meshes already loaded, by file and material override.
+    meshes: HashMap<(String, Option<String>), Arc<dyn Hittable>>,
+}
+
+impl<'a> Loader<'a> {
+    fn error<T>(&self, offset: usize, msg: String) -> Result<T, SceneError> {
+        let line = self.src[..offset].matches('\n').count() + 1;
+        Err(SceneError::Invalid { line, msg })
+    }
+
+    /// Error in the table `[kind.name]`. toml does not keep the position of tables, so the
+    /// header is searched for in the source, with the name bare or quoted.
+    fn table_error<T>(&self, kind: &str, name: &str, msg: String) -> Result<T, SceneError> {
+        let offset = [
+            format!("[{}.{}]", kind, name),
+            format!("[{}.\"{}\"]", kind, name),
+            format!("[{}.'{}']", kind, name),
+        ]
+        .iter()
+        .find_map(|header| self.src.find(header));
+        self.error(offset.unwrap_or(0), msg)
+    }
+
+    fn texture(&self, name: &str, desc: TextureDesc) -> Result<Arc<dyn Texture>, SceneError> {
+        Ok(match desc {
+            TextureDesc::Checker { even, odd, scale } => {
+                if !scale.is_finite() || scale == 0.0 {
+                    let msg = format!("invalid checker scale {}", scale);
+                    return self.table_error("textures", name, msg);
+                }
+                Arc::new(Checker::new(Vec3::from(even), Vec3::from(odd), scale))
+            }
+            TextureDesc::Image { file, wrap } => {
+                let mut img = match ImageTexture::load(self.dir.join(&file)) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        let msg = format!("could not load image {}: {}", file, e);
+                        return self.table_error("textures", name, msg);
+                    }
+                };
+                if let Some(wrap) = wrap {
+                    img.wrap = match wrap.parse() {
+                        Ok(x) => x,
+                        Err(msg) => return self.table_error("textures", name, msg),
+                    };
+                }
+                Arc::new(img)
+            }
+            TextureDesc::Noise {
+                pattern,
+                scale,
+                octaves,
+                colors,
+                seed,
+            } => {
+                let pattern = match pattern.parse() {
+                    Ok(x) => x,
+                    Err(msg) => return self.table_error("textures", name, msg),
+                };
+                if !scale.is_finite() || scale == 0.0 {
+                    let msg = format!("invalid noise scale {}", scale);
+                    return self.table_error("textures", name, msg);
+                }
+                let mut noise = NoiseTexture::new(pattern, scale, seed);
+                if let Some(octaves) = octaves {
+                    noise.octaves = octaves;
+                }
+                if let Some([low, high]) = colors {
+                    noise.low = Vec3::from(low);
+                    noise.high = Vec3::from(high);
+                }
+                Arc::new(noise)
+            }
+        })
+    }
+
+    fn color(&self, material: &str, desc: ColorDesc) -> Result<Arc<dyn Texture>, SceneError> {
+        match desc {
+            ColorDesc::Color(c) => Ok(Arc::new(Vec3::from(c))),
+            ColorDesc::Texture(name) => match self.textures.get(&name) {
+                Some(t) => Ok(t.clone()),
+                None => {
+                    let msg = format!("unknown texture `{}`", name);
+                    self.table_error("materials", material, msg)
+                }
+            },
+        }
+    }
+
+    fn make_material(
+        &self,
+        name: &str,
+        desc: MaterialDesc,
+    ) -> Result<Arc<dyn Material>, SceneError> {
+        Ok(match desc {
+            MaterialDesc::Lambertian { albedo } => {
+                Arc::new(Lambertian::new(self.color(name, albedo)?))
+            }
+            MaterialDesc::Metal { albedo, fuzz } => {
+                if !(0.0..=1.0).contains(&fuzz) {
+                    let msg = format!("metal fuzz must be between 0 and 1, got {}", fuzz);
+                    return self.table_error("materials", name, msg);
+                }
+                Arc::new(Metal::new(self.color(name, albedo)?, fuzz))
+            }
+            MaterialDesc::Dielectric { ref_idx } => {
+                if ref_idx.is_nan() || ref_idx <= 0.0 {
+                    let msg = format!("dielectric ref_idx must be positive, got {}", ref_idx);
+                    return self.table_error("materials", name, msg);
+                }
+                Arc::new(Dielectric::new(ref_idx))
+            }
+            MaterialDesc::Light { emit } => Arc::new(DiffuseLight::new(self.color(name, emit)?)),
+            MaterialDesc::Isotropic { albedo } => {
+                Arc::new(Isotropic::new(self.color(name, albedo)?))
+            }
+        })
+    }
+
+    fn material(&self, name: &Spanned<String>) -> Result<Arc<dyn Material>, SceneError> {
+        match self.materials.get(name.get_ref()) {
+            Some(m) => Ok(m.clone()),
+            None => self.error(
+                name.start(),
+                format!("unknown material `{}`", name.get_ref()),
+            ),
+        }
+    }
+
+    /// Fills `boundary` with a medium of the given density if there is one.
+    fn volume<H: Hittable + 'static>(
+        &self,
+        boundary: H,
+        density: Option<Spanned<f32>>,
+        mat: Arc<dyn Material>,
+    ) -> Result<Box<dyn Hittable>, SceneError> {
+        match density {
+            None => Ok(Box::new(boundary)),
+            Some(density) => {
+                let v = *density.get_ref();
+                if v.is_nan() || v <= 0.0 {
+                    return self.error(
+                        density.start(),
+                        format!("density must be positive, got {}", v),
+                    );
+                }
+                Ok(Box::new(ConstantMedium::new(boundary, v, mat)))
+            }
+        }
+    }
+
+    fn transform(
+        &self,
+        scale: Option<Spanned<ScaleDesc>>,
+        rotate: Option<[f32; 3]>,
+        translate: Option<[f32; 3]>,
+    ) -> Result<Option<Transform>, SceneError> {
+        if scale.is_none() && rotate.is_none() && translate.is_none() {
+            return Ok(None);
+        }
+
+        let mut t = Transform::identity();
+        if let Some(scale) = scale {
+            let v = match *scale.get_ref() {
+                ScaleDesc::Uniform(x) => Vec3::broadcast(x),
+                ScaleDesc::PerAxis(v) => Vec3::from(v),
+            };
+            if v.x == 0.0 || v.y == 0.0 || v.z == 0.0 {
+                return self.error(scale.start(), "scale must not be zero".to_owned());
+            }
+            t = t.then(&Transform::scale(v));
+        }
+        if let Some([x, y, z]) = rotate {
+            t = t
+                .then(&Transform::rotation_x(x.to_radians()))
+                .then(&Transform::rotation_y(y.to_radians()))
+                .then(&Transform::rotation_z(z.to_radians()));
+        }
+        if let Some(v) = translate {
+            t = t.then(&Transform::translation(Vec3::from(v)));
+        }
+        Ok(Some(t))
+    }
+
+    fn mesh(&mut self, desc: MeshDesc) -> Result<Box<dyn Hittable>, SceneError> {
+        let key = (
+            desc.file.get_ref().clone(),
+            desc.material.as_ref().map(|x| x.get_ref().clone()),
+        );
+
+        let mesh = match self.meshes.get(&key) {
+            Some(mesh) => mesh.clone(),
+            None => {
+                let groups = match obj::load(self.dir.join(desc.file.get_ref())) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        let msg = format!("could not load mesh: {}", e);
+                        return self.error(desc.file.start(), msg);
+                    }
+                };
+
+                let mat = match &desc.material {
+                    Some(name) => Some(self.material(name)?),
+                    None => None,
+                };
+
+                let groups: Vec<_> = groups
+                    .into_iter()
+                    .map(|g| {
+                        let mat = mat.clone().unwrap_or(g.material);
+                        TriangleMesh::new(g.positions, g.normals, g.uvs, g.indices, mat)
+                    })
+                    .collect();
+                let mesh: Arc<dyn Hittable> = Arc::new(LinearBVH::new(groups));
+                self.meshes.insert(key, mesh.clone());
+                mesh
+            }
+        };
+
+        Ok(
+            match self.transform(desc.scale, desc.rotate, desc.translate)? {
+                Some(t) => Box::new(Transformed::new(mesh, t)),
+                None => Box::new(mesh),
+            },
+        )
+    }
+
+    fn grid_volume(&self, desc: VolumeDesc) -> Result<Box<dyn Hittable>, SceneError> {
+        let start = desc.grid.start();
+        let grid = match desc.grid.into_inner() {
+            GridDesc::File(file) => match DensityGrid::load(self.dir.join(&file)) {
+                Ok(x) => x,
+                Err(e) => return self.error(start, format!("could not load grid {}: {}", file, e)),
+            },
+            GridDesc::Cloud {
+                min,
+                max,
+                scale,
+                resolution,
+                seed,
+            } => {
+                let (lo, hi) = (Vec3::from(min), Vec3::from(max));
+                if !(lo.x < hi.x && lo.y < hi.y && lo.z < hi.z) {
+                    return self.error(start, "cloud min must be below max".to_owned());
+                }
+                if resolution == 0 || resolution > 1024 {
+                    return self.error(
+                        start,
+                        format!(
+                            "cloud resolution must be between 1 and 1024, got {}",
+                            resolution
+                        ),
+                    );
+                }
+                DensityGrid::cloud([resolution; 3], AABB::new(lo, hi), scale, seed)
+            }
+        };
+
+        for coef in &[&desc.absorption, &desc.scattering] {
+            let v = *coef.get_ref();
+            if v.is_nan() || v < 0.0 {
+                return self.error(
+                    coef.start(),
+                    format!("volume coefficients must not be negative, got {}", v),
+                );
+            }
+        }
+
+        let phase: Arc<dyn Material> = match &desc.material {
+            Some(name) => self.material(name)?,
+            None => Arc::new(Isotropic::new(Color::one())),
+        };
+        let medium = GridMedium::new(
+            Arc::new(grid),
+            *desc.absorption.get_ref(),
+            *desc.scattering.get_ref(),
+            desc.emission.map_or(Color::zero(), Vec3::from),
+            phase,
+        );
+        Ok(
+            match self.transform(desc.scale, desc.rotate, desc.translate)? {
+                Some(t) => Box::new(Transformed::new(medium, t)),
+                None => Box::new(medium),
+            },
+        )
+    }
+
+    fn camera(&self, desc: CameraDesc) -> Result<Camera, SceneError> {
+        let mut cam = Camera::default();
+        if let Some(pos) = desc.pos {
+            cam.pos = Vec3::from(pos);
+        }
+        if let Some(up) = desc.up {
+            let v = Vec3::from(*up.get_ref());
+            if !v.mag_sq().is_normal() {
+                return self.error(
+                    up.start(),
+                    format!(
+                        "camera up must be a non-zero vector, got {:?}",
+                        up.get_ref()
+                    ),
+                );
+            }
+            cam.up = v.normalized();
+        }
+        if let Some(fov) = desc.fov {
+            let v = *fov.get_ref();
+            if !(v > 0.0 && v < 180.0) {
+                return self.error(
+                    fov.start(),
+                    format!("camera fov must be between 0 and 180 degrees, got {}", v),
+                );
+            }
+            cam.fov = v;
+        }
+        if let Some(look_at) = desc.look_at {
+            let dir = cam.pos - Vec3::from(*look_at.get_ref());
+            if dir.mag_sq() == 0.0 || dir.cross(cam.up).mag_sq() == 0.0 {
+                return self.error(
+                    look_at.start(),
+                    "camera look_at must differ from pos and not be aligned with up".to_owned(),
+                );
+            }
+            cam.eye = dir.normalized();
+            cam.focus_dist = dir.mag();
+        }
+        if let Some(aperture) = desc.aperture {
+            let v = *aperture.get_ref();
+            if v.is_nan() || v < 0.0 {
+                return self.error(
+                    aperture.start(),
+                    format!("camera aperture must not be negative, got {}", v),
+                );
+            }
+            cam.aperture = v;
+        }
+        if let Some(focus_dist) = desc.focus_dist {
+            let v = *focus_dist.get_ref();
+            if v.is_nan() || v <= 0.0 {
+                return self.error(
+                    focus_dist.start(),
+                    format!("camera focus_dist must be positive, got {}", v),
+                );
+            }
+            cam.focus_dist = v;
+        }
+        if let Some(blades) = desc.blades {
+            cam.blades = blades;
+        }
+        if let Some(shutter) = desc.shutter {
+            let [open, close] = *shutter.get_ref();
+            if open.is_nan() || close.is_nan() || close < open {
+                return self.error(
+                    shutter.start(),
+                    "camera shutter must close after it opens".to_owned(),
+                );
+            }
+            cam.shutter_open = open;
+            cam.shutter_close = close;
+        }
+        Ok(cam)
+    }
+}
+
+/// Parses a scene from the contents of a TOML file, meshes are looked up relative to `dir`.
+pub fn from_str(src: &str, dir: &Path) -> Result<RayTracer<Box<dyn Hittable>>, SceneError> {
+    let desc: SceneDesc = toml::from_str(src)?;
+
+    let mut loader = Loader {
+        src,
+        dir,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        meshes: HashMap::new(),
+    };
+    for (name, t) in desc.textures {
+        let texture = loader.texture(&name, t)?;
+        loader.textures.insert(name, texture);
+    }
+    for (name, m) in desc.materials {
+        let material = loader.make_material(&name, m)?;
+        loader.materials.insert(name, material);
+    }
+
+    let cam = loader.camera(desc.camera)?;
+
+    let background = match desc.background {
+        None => Background::Sky,
+        Some(bg) => {
+            let start = bg.start();
+            match bg.into_inner() {
+                BackgroundDesc::Color(c) => Background::Solid(Vec3::from(c)),
+                BackgroundDesc::Named(ref x) if x == "sky" => Background::Sky,
+                BackgroundDesc::Named(x) => {
+                    return loader.error(start, format!("unknown background `{}`", x))
+                }
+            }
+        }
+    };
+
+    let mut objects: Vec<Box<dyn Hittable>> = vec![];
+    for s in desc.spheres {
+        let radius = *s.radius.get_ref();
+        if radius.is_nan() || radius <= 0.0 {
+            return loader.error(
+                s.radius.start(),
+                format!("sphere radius must be positive, got {}", radius),
+            );
+        }
+        let mat = loader.material(&s.material)?;
+        let sphere: Box<dyn Hittable> = match s.center1 {
+            None => Box::new(Sphere {
+                center: Vec3::from(s.center),
+                radius,
+                mat: mat.clone(),
+            }),
+            Some(center1) => Box::new(MovingSphere {
+                center0: Vec3::from(s.center),
+                center1: Vec3::from(center1),
+                time0: 0.0,
+                time1: 1.0,
+                radius,
+                mat: mat.clone(),
+            }),
+        };
+        objects.push(loader.volume(sphere, s.density, mat)?);
+    }
+
+    for t in desc.triangles {
+        let [a, b, c] = t.vertices.get_ref().map(Vec3::from);
+        let area = (b - a).cross(c - a).mag_sq();
+        if area.is_nan() || area == 0.0 {
+            return loader.error(
+                t.vertices.start(),
+                "triangle vertices must not be aligned".to_owned(),
+            );
+        }
+        let mut tri = Triangle::new([a, b, c], loader.material(&t.material)?);
+        if let Some(normals) = t.normals {
+            let n = normals.get_ref().map(Vec3::from);
+            if n.iter().any(|n| n.mag_sq().is_nan() || n.mag_sq() == 0.0) {
+                return loader.error(
+                    normals.start(),
+                    "triangle normals must not be zero".to_owned(),
+                );
+            }
+            tri.normals = Some(n.map(|n| n.normalized()));
+        }
+        objects.push(Box::new(tri));
+    }
+
+    for q in desc.quads {
+        let (u, v) = (Vec3::from(*q.u.get_ref()), Vec3::from(*q.v.get_ref()));
+        let area = u.cross(v).mag_sq();
+        if area.is_nan() || area == 0.0 {
+            return loader.error(
+                q.u.start().min(q.v.start()),
+                "quad sides u and v must not be parallel".to_owned(),
+            );
+        }
+        objects.push(Box::new(Quad::new(
+            Vec3::from(q.origin),
+            u,
+            v,
+            loader.material(&q.material)?,
+        )));
+    }
+
+    for b in desc.boxes {
+        let mat = loader.material(&b.material)?;
+        let cuboid = Cuboid::new(Vec3::from(b.min), Vec3::from(b.max), mat.clone());
+        let cuboid: Box<dyn Hittable> = match loader.transform(b.scale, b.rotate, b.translate)? {
+            Some(t) => Box::new(Transformed::new(cuboid, t)),
+            None => Box::new(cuboid),
+        };
+        objects.push(loader.volume(cuboid, b.density, mat)?);
+    }
+
+    for m in desc.meshes {
+        objects.push(loader.mesh(m)?);
+    }
+
+    for v in desc.volumes {
+        objects.push(loader.grid_volume(v)?);
+    }
+
+    let mut tracer = RayTracer::new(Box::new(LinearBVH::new(objects)) as Box<dyn Hittable>);
+    tracer.cam = cam;
+    tracer.background = background;
+    tracer.init();
+    Ok(tracer)
+}
+
+/// Reads and parses a scene file.
+pub fn load(path: impl AsRef<Path>) -> Result<RayTracer<Box<dyn Hittable>>, SceneError> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    from_str(&std::fs::read_to_string(path)?, dir)
+}
@@ -0,0 +1,87 @@
+pub mod file;
+pub mod obj;
+
+use crate::bvh::LinearBVH;
+use crate::hittable::sphere::Sphere;
+use crate::hittable::Hittable;
+use crate::material::{Dielectric, Lambertian, Metal};
+use crate::raytrace::vec3;
+use crate::utils::SampleRng;
+use rand::{Rng, SeedableRng};
+use ultraviolet::Vec3;
+
+/// The final scene of "Ray Tracing in One Weekend": a large ground sphere, three big spheres and
+/// a grid of small randomly placed spheres with random materials, generated from `seed`.
+pub fn random_scene(seed: u64) -> impl Hittable {
+    let mut rng = SampleRng::seed_from_u64(seed);
+    let mut objects: Vec<Box<dyn Hittable>> = vec![];
+
+    let ground = Box::new(Sphere {
+        center: vec3(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        mat: Lambertian::new(Vec3::broadcast(0.5)),
+    });
+
+    for a in -5..5 {
+        for b in -5..5 {
+            let choose_mat = rng.gen::<f32>();
+            let center = vec3(
+                a as f32 + 0.9 * rng.gen::<f32>(),
+                0.2,
+                b as f32 + 0.9 * rng.gen::<f32>(),
+            );
+
+            if (center - vec3(4.0, 0.2, 0.0)).mag() > 0.9 {
+                if choose_mat < 0.8 {
+                    // diffuse
+                    let albedo =
+                        Vec3::from(rng.gen::<[f32; 3]>()) * Vec3::from(rng.gen::<[f32; 3]>());
+                    objects.push(Box::new(Sphere {
+                        center,
+                        radius: 0.2,
+                        mat: Lambertian::new(albedo),
+                    }));
+                } else if choose_mat < 0.95 {
+                    // metal
+                    let albedo = Vec3::broadcast(0.5) + Vec3::from(rng.gen::<[f32; 3]>()) * 0.5;
+                    let fuzz = rng.gen::<f32>() * 0.5;
+                    objects.push(Box::new(Sphere {
+                        center,
+                        radius: 0.2,
+                        mat: Metal::new(albedo, fuzz),
+                    }));
+                } else {
+                    // glass
+                    objects.push(Box::new(Sphere {
+                        center,
+                        radius: 0.2,
+                        mat: Dielectric::new(1.5),
+                    }));
+                }
+            }
+        }
+    }
+
+    objects.push(Box::new(Sphere {
+        center: vec3(0.0, 1.0, 0.0),
+        radius: 1.0,
+        mat: Dielectric::new(1.5),
+    }));
+
+    objects.push(Box::new(Sphere {
+        center: vec3(-4.0, 1.0, 0.0),
+        radius: 1.0,
+        mat: Lambertian::new(vec3(0.4, 0.2, 0.1)),
+    }));
+
+    objects.push(Box::new(Sphere {
+        center: vec3(4.0, 1.0, 0.0),
+        radius: 1.0,
+        mat: Metal::new(vec3(0.7, 0.6, 0.5), 0.0),
+    }));
+
+    vec![
+        ground as Box<dyn Hittable>,
+        Box::new(LinearBVH::new(objects)),
+    ]
+}
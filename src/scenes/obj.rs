@@ -0,0 +1,555 @@
+//! Wavefront OBJ and MTL import.
+//!
+//! Faces with more than three vertices are triangulated as fans, so they are expected to be
+//! convex. Every group (`g` or `o`) and material (`usemtl`) combination becomes its own
+//! [`ObjGroup`], that can be turned into a [`TriangleMesh`].
+//!
+//! MTL materials are mapped onto the materials of this crate: transparent ones (`d < 1`,
+//! `Tr > 0` or a refractive `illum`) become [`Dielectric`] with `Ni` as refractive index,
+//! reflective ones (`illum` 3 or 5) become [`Metal`] using `Ks` and `Ns`, and everything else is
+//! [`Lambertian`] using `Kd`, or the image given by `map_Kd`. Materials with a non-black `Ke`
+//! are [`DiffuseLight`]s.
+
+use crate::hittable::mesh::TriangleMesh;
+use crate::hittable::Hittable;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::texture::{ImageTexture, Texture};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use ultraviolet::{Vec2, Vec3};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(PathBuf, std::io::Error),
+    Syntax {
+        file: PathBuf,
+        line: usize,
+        msg: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            ObjError::Syntax { file, line, msg } => {
+                write!(f, "{}:{}: {}", file.display(), line, msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Triangles of an OBJ file sharing a group and a material, with de-indexed vertex attributes:
+/// `positions`, `normals` and `uvs` all have the same length.
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Arc<dyn Material>,
+    pub positions: Vec<Vec3>,
+    /// Only present if every face of the group specifies normals.
+    pub normals: Option<Vec<Vec3>>,
+    /// Only present if every face of the group specifies texture coordinates.
+    pub uvs: Option<Vec<Vec2>>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl ObjGroup {
+    pub fn into_mesh(self) -> TriangleMesh<Arc<dyn Material>> {
+        TriangleMesh::new(
+            self.positions,
+            self.normals,
+            self.uvs,
+            self.indices,
+            self.material,
+        )
+    }
+}
+
+struct LineParser<'a> {
+    file: &'a Path,
+    line: usize,
+}
+
+impl<'a> LineParser<'a> {
+    fn error<T>(&self, msg: String) -> Result<T, ObjError> {
+        Err(ObjError::Syntax {
+            file: self.file.to_owned(),
+            line: self.line,
+            msg,
+        })
+    }
+
+    fn floats<'s>(
+        &self,
+        args: impl Iterator<Item = &'s str>,
+        min: usize,
+        max: usize,
+    ) -> Result<Vec<f32>, ObjError> {
+        let mut v = Vec::with_capacity(max);
+        for x in args {
+            match x.parse() {
+                Ok(f) => v.push(f),
+                Err(_) => return self.error(format!("invalid number `{}`", x)),
+            }
+        }
+        if v.len() < min || v.len() > max {
+            return self.error(format!(
+                "expected {} to {} numbers, found {}",
+                min,
+                max,
+                v.len()
+            ));
+        }
+        Ok(v)
+    }
+
+    fn vec3<'s>(&self, args: impl Iterator<Item = &'s str>) -> Result<Vec3, ObjError> {
+        let v = self.floats(args, 3, 4)?;
+        Ok(Vec3::new(v[0], v[1], v[2]))
+    }
+
+    /// Resolves a 1-based or negative (relative to the end) OBJ index.
+    fn index(&self, x: &str, count: usize, what: &str) -> Result<usize, ObjError> {
+        let i: isize = match x.parse() {
+            Ok(i) => i,
+            Err(_) => return self.error(format!("invalid {} index `{}`", what, x)),
+        };
+        let resolved = if i > 0 { i - 1 } else { count as isize + i };
+        if i == 0 || resolved < 0 || resolved >= count as isize {
+            return self.error(format!(
+                "{} index {} out of range, {} defined so far",
+                what, i, count
+            ));
+        }
+        Ok(resolved as usize)
+    }
+}
+
+/// Index of a vertex attribute in the file, `usize::MAX` when absent.
+type VertexKey = (usize, usize, usize);
+
+struct GroupBuilder {
+    name: String,
+    material: String,
+    vertices: HashMap<VertexKey, u32>,
+    keys: Vec<VertexKey>,
+    indices: Vec<[u32; 3]>,
+    has_normals: bool,
+    has_uvs: bool,
+}
+
+impl GroupBuilder {
+    fn new(name: String, material: String) -> Self {
+        Self {
+            name,
+            material,
+            vertices: HashMap::new(),
+            keys: vec![],
+            indices: vec![],
+            has_normals: true,
+            has_uvs: true,
+        }
+    }
+
+    fn vertex(&mut self, key: VertexKey) -> u32 {
+        self.has_uvs &= key.1 != usize::MAX;
+        self.has_normals &= key.2 != usize::MAX;
+        let keys = &mut self.keys;
+        *self.vertices.entry(key).or_insert_with(|| {
+            keys.push(key);
+            (keys.len() - 1) as u32
+        })
+    }
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    std::fs::read_to_string(path).map_err(|e| ObjError::Io(path.to_owned(), e))
+}
+
+fn default_material() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Vec3::broadcast(0.8)))
+}
+
+/// Parses the contents of a MTL file, `file` is only used for error messages.
+pub fn parse_mtl(src: &str, file: &Path) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    struct Mtl {
+        kd: Vec3,
+        ks: Vec3,
+        ke: Vec3,
+        map_kd: Option<Arc<dyn Texture>>,
+        ns: f32,
+        ni: f32,
+        /// Line of the `Ni` statement, or of `newmtl` when there is none.
+        ni_line: usize,
+        opacity: f32,
+        illum: u32,
+    }
+
+    fn finish(m: &Mtl, file: &Path) -> Result<Arc<dyn Material>, ObjError> {
+        Ok(if m.ke.component_max() > 0.0 {
+            Arc::new(DiffuseLight::new(m.ke))
+        } else if m.opacity < 1.0 || [4, 6, 7, 9].contains(&m.illum) {
+            // Opaque materials often have `Ni 0`, it only matters for transparent ones.
+            if m.ni.is_nan() || m.ni <= 0.0 {
+                let p = LineParser {
+                    file,
+                    line: m.ni_line,
+                };
+                return p.error(format!("invalid refractive index {}", m.ni));
+            }
+            Arc::new(Dielectric::new(m.ni))
+        } else if m.illum == 3 || m.illum == 5 {
+            // Phong exponents go up to ~1000 for mirrors, map them to a roughness.
+            let fuzz = (2.0 / (m.ns + 2.0)).sqrt();
+            Arc::new(Metal::new(m.ks, fuzz))
+        } else if let Some(map) = &m.map_kd {
+            Arc::new(Lambertian::new(map.clone()))
+        } else {
+            Arc::new(Lambertian::new(m.kd))
+        })
+    }
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Mtl)> = None;
+    let mut p = LineParser { file, line: 0 };
+
+    for (i, line) in src.lines().enumerate() {
+        p.line = i + 1;
+        let mut args = line.split_whitespace();
+        let keyword = match args.next() {
+            Some(x) if !x.starts_with('#') => x,
+            _ => continue,
+        };
+
+        if keyword == "newmtl" {
+            let name = args.collect::<Vec<_>>().join(" ");
+            if name.is_empty() {
+                return p.error("missing material name".to_owned());
+            }
+            if let Some((name, m)) = current.take() {
+                materials.insert(name, finish(&m, file)?);
+            }
+            current = Some((
+                name,
+                Mtl {
+                    kd: Vec3::broadcast(0.8),
+                    ks: Vec3::zero(),
+                    ke: Vec3::zero(),
+                    map_kd: None,
+                    ns: 0.0,
+                    ni: 1.5,
+                    ni_line: p.line,
+                    opacity: 1.0,
+                    illum: 2,
+                },
+            ));
+            continue;
+        }
+
+        let m = match &mut current {
+            Some((_, m)) => m,
+            None => return p.error(format!("`{}` before any `newmtl`", keyword)),
+        };
+
+        match keyword {
+            "Kd" => m.kd = p.vec3(args)?,
+            "Ks" => m.ks = p.vec3(args)?,
+            "Ke" => m.ke = p.vec3(args)?,
+            "Ns" => m.ns = p.floats(args, 1, 1)?[0],
+            "Ni" => {
+                m.ni = p.floats(args, 1, 1)?[0];
+                m.ni_line = p.line;
+            }
+            "d" => m.opacity = p.floats(args, 1, 1)?[0],
+            "Tr" => m.opacity = 1.0 - p.floats(args, 1, 1)?[0],
+            "illum" => {
+                let x = args.next().unwrap_or("");
+                m.illum = match x.parse() {
+                    Ok(x) => x,
+                    Err(_) => return p.error(format!("invalid illumination model `{}`", x)),
+                }
+            }
+            "map_Kd" => {
+                // Options such as `-s` are not supported, the file name comes last.
+                let name = match args.last() {
+                    Some(x) => x,
+                    None => return p.error("missing texture file".to_owned()),
+                };
+                let dir = file.parent().unwrap_or_else(|| Path::new(""));
+                match ImageTexture::load(dir.join(name)) {
+                    Ok(img) => m.map_kd = Some(Arc::new(img)),
+                    Err(e) => return p.error(format!("could not load texture {}: {}", name, e)),
+                }
+            }
+            // Other maps and statements are not supported yet.
+            _ => {}
+        }
+    }
+
+    if let Some((name, m)) = current {
+        materials.insert(name, finish(&m, file)?);
+    }
+
+    Ok(materials)
+}
+
+/// Parses the contents of an OBJ file. `file` is used for error messages and to find the
+/// material libraries, which are relative to it. Files without faces are an error.
+pub fn parse(src: &str, file: &Path) -> Result<Vec<ObjGroup>, ObjError> {
+    let dir = file.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut positions: Vec<Vec3> = vec![];
+    let mut normals: Vec<Vec3> = vec![];
+    let mut uvs: Vec<Vec2> = vec![];
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+
+    let mut groups: Vec<GroupBuilder> = vec![];
+    let mut current_group = String::new();
+    let mut current_mat = String::new();
+    let mut current: Option<usize> = None;
+
+    let mut p = LineParser { file, line: 0 };
+
+    for (i, line) in src.lines().enumerate() {
+        p.line = i + 1;
+        let mut args = line.split_whitespace();
+        let keyword = match args.next() {
+            Some(x) if !x.starts_with('#') => x,
+            _ => continue,
+        };
+
+        match keyword {
+            "v" => positions.push(p.vec3(args)?),
+            "vn" => normals.push(p.vec3(args)?.normalized()),
+            "vt" => {
+                let v = p.floats(args, 1, 3)?;
+                uvs.push(Vec2::new(v[0], v.get(1).copied().unwrap_or(0.0)));
+            }
+            "g" | "o" => {
+                current_group = args.collect::<Vec<_>>().join(" ");
+                current = None;
+            }
+            "usemtl" => {
+                let name = args.collect::<Vec<_>>().join(" ");
+                if !materials.contains_key(&name) {
+                    return p.error(format!("unknown material `{}`", name));
+                }
+                current_mat = name;
+                current = None;
+            }
+            "mtllib" => {
+                for lib in args {
+                    let path = dir.join(lib);
+                    materials.extend(parse_mtl(&read(&path)?, &path)?);
+                }
+            }
+            "f" => {
+                let mut face = vec![];
+                for vert in args {
+                    let mut parts = vert.split('/');
+                    let v = p.index(parts.next().unwrap_or(""), positions.len(), "vertex")?;
+                    let vt = match parts.next() {
+                        None | Some("") => usize::MAX,
+                        Some(x) => p.index(x, uvs.len(), "texture coordinate")?,
+                    };
+                    let vn = match parts.next() {
+                        None | Some("") => usize::MAX,
+                        Some(x) => p.index(x, normals.len(), "normal")?,
+                    };
+                    if parts.next().is_some() {
+                        return p.error(format!("invalid face vertex `{}`", vert));
+                    }
+                    face.push((v, vt, vn));
+                }
+                if face.len() < 3 {
+                    return p.error(format!("face with only {} vertices", face.len()));
+                }
+
+                let g = match current {
+                    Some(g) => g,
+                    None => {
+                        let existing = groups
+                            .iter()
+                            .position(|g| g.name == current_group && g.material == current_mat);
+                        let g = existing.unwrap_or_else(|| {
+                            groups.push(GroupBuilder::new(
+                                current_group.clone(),
+                                current_mat.clone(),
+                            ));
+                            groups.len() - 1
+                        });
+                        current = Some(g);
+                        g
+                    }
+                };
+                let group = &mut groups[g];
+
+                let first = group.vertex(face[0]);
+                for w in face[1..].windows(2) {
+                    let b = group.vertex(w[0]);
+                    let c = group.vertex(w[1]);
+                    group.indices.push([first, b, c]);
+                }
+            }
+            // Smoothing groups, lines, points and free-form geometry are ignored.
+            _ => {}
+        }
+    }
+
+    // Groups are only created by faces, and an empty hierarchy would have no bounding box.
+    if groups.is_empty() {
+        return p.error("no faces in the file".to_owned());
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|g| ObjGroup {
+            material: materials
+                .get(&g.material)
+                .cloned()
+                .unwrap_or_else(default_material),
+            positions: g.keys.iter().map(|k| positions[k.0]).collect(),
+            normals: if g.has_normals {
+                Some(g.keys.iter().map(|k| normals[k.2]).collect())
+            } else {
+                None
+            },
+            uvs: if g.has_uvs {
+                Some(g.keys.iter().map(|k| uvs[k.1]).collect())
+            } else {
+                None
+            },
+            indices: g.indices,
+            name: g.name,
+        })
+        .collect())
+}
+
+/// Reads an OBJ file along with its material libraries.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<ObjGroup>, ObjError> {
+    let path = path.as_ref();
+    parse(&read(path)?, path)
+}
+
+/// Reads an OBJ file and returns one mesh per group, ready to be put in a [`BVH`].
+///
+/// [`BVH`]: crate::bvh::BVH
+pub fn load_meshes(path: impl AsRef<Path>) -> Result<Vec<Box<dyn Hittable>>, ObjError> {
+    Ok(load(path)?
+        .into_iter()
+        .map(|g| Box::new(g.into_mesh()) as Box<dyn Hittable>)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(src: &str) -> Result<Vec<ObjGroup>, ObjError> {
+        parse(src, Path::new("test.obj"))
+    }
+
+    /// Line and message of a syntax error.
+    fn syntax_error<T>(result: Result<T, ObjError>) -> (usize, String) {
+        match result {
+            Err(ObjError::Syntax { line, msg, .. }) => (line, msg),
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("no error"),
+        }
+    }
+
+    fn xyz(v: Vec3) -> [f32; 3] {
+        [v.x, v.y, v.z]
+    }
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    #[test]
+    fn quads_are_triangulated_as_fans() {
+        let groups = parse_str(&format!("{}f 1 2 3 4\n", SQUARE)).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].indices, [[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(xyz(groups[0].positions[3]), [0.0, 1.0, 0.0]);
+        assert!(groups[0].normals.is_none() && groups[0].uvs.is_none());
+    }
+
+    #[test]
+    fn negative_indices_are_relative_to_the_end() {
+        let src = format!("{}f -4 -3 -2\nv 5 5 5\nf -1 1 2\n", SQUARE);
+        let groups = parse_str(&src).unwrap();
+        let g = &groups[0];
+        let face = |i: usize| g.indices[i].map(|j| xyz(g.positions[j as usize]));
+        let (a, b, c) = ([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]);
+        assert_eq!(face(0), [a, b, c]);
+        assert_eq!(face(1), [[5.0; 3], a, b]);
+    }
+
+    #[test]
+    fn vertex_attributes() {
+        let src = format!(
+            "{}vt 0 0\nvt 1 0\nvt 1 1\nvn 0 0 2\nf 1/1/1 2/2/1 3/3/1\ng normals\nf 1//1 3//1 4//1\n",
+            SQUARE
+        );
+        let groups = parse_str(&src).unwrap();
+        assert_eq!(groups.len(), 2);
+
+        let uvs = groups[0].uvs.as_ref().unwrap();
+        assert_eq!([uvs[2].x, uvs[2].y], [1.0, 1.0]);
+        // Normals are normalized when read.
+        assert_eq!(xyz(groups[0].normals.as_ref().unwrap()[0]), [0.0, 0.0, 1.0]);
+
+        assert_eq!(groups[1].name, "normals");
+        assert!(groups[1].uvs.is_none());
+        assert_eq!(groups[1].normals.as_ref().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let (line, msg) = syntax_error(parse_str(&format!("{}f 1 2 5\n", SQUARE)));
+        assert_eq!(line, 5);
+        assert!(msg.contains("vertex index 5 out of range"), "{}", msg);
+
+        let (_, msg) = syntax_error(parse_str(&format!("{}f 1 2 -5\n", SQUARE)));
+        assert!(msg.contains("out of range"), "{}", msg);
+        let (_, msg) = syntax_error(parse_str(&format!("{}f 1 2 0\n", SQUARE)));
+        assert!(msg.contains("out of range"), "{}", msg);
+        let (_, msg) = syntax_error(parse_str(&format!("{}f 1//1 2//1 3//1\n", SQUARE)));
+        assert!(msg.contains("normal index 1 out of range"), "{}", msg);
+    }
+
+    #[test]
+    fn degenerate_face() {
+        let (line, msg) = syntax_error(parse_str(&format!("{}f 1 2\n", SQUARE)));
+        assert_eq!(line, 5);
+        assert!(msg.contains("only 2 vertices"), "{}", msg);
+    }
+
+    #[test]
+    fn unknown_material() {
+        let (line, msg) = syntax_error(parse_str(&format!("{}usemtl red\nf 1 2 3\n", SQUARE)));
+        assert_eq!(line, 5);
+        assert!(msg.contains("unknown material `red`"), "{}", msg);
+    }
+
+    #[test]
+    fn no_faces() {
+        let (_, msg) = syntax_error(parse_str(SQUARE));
+        assert!(msg.contains("no faces"), "{}", msg);
+    }
+
+    #[test]
+    fn mtl_refractive_index() {
+        let mtl = |src: &str| parse_mtl(src, Path::new("test.mtl"));
+        let materials = mtl("newmtl glass\nd 0.5\nNi 1.33\nnewmtl opaque\nNi 0\n").unwrap();
+        assert_eq!(materials.len(), 2);
+
+        let (line, msg) = syntax_error(mtl("newmtl glass\nNi 0\nd 0.5\n"));
+        assert_eq!(line, 2);
+        assert!(msg.contains("invalid refractive index 0"), "{}", msg);
+        let (line, _) = syntax_error(mtl("newmtl a\nNi nan\nillum 7\nnewmtl b\n"));
+        assert_eq!(line, 2);
+    }
+}
@@ -0,0 +1,199 @@
+//! Colors varying over surfaces, used as the albedo of materials.
+//!
+//! A constant [`Color`] is the simplest texture, so materials can be given either.
+
+pub mod noise;
+
+use crate::raytrace::{vec3, Color};
+use crate::tonemap::srgb_decode;
+use std::fmt;
+use std::ops::Deref;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use ultraviolet::{Vec2, Vec3};
+
+pub trait Texture: Send + Sync {
+    /// Color at the surface coordinates `uv` of the point `p`.
+    fn value(&self, uv: Vec2, p: Vec3) -> Color;
+}
+
+impl Texture for Color {
+    fn value(&self, _uv: Vec2, _p: Vec3) -> Color {
+        *self
+    }
+}
+
+impl Texture for Arc<dyn Texture> {
+    fn value(&self, uv: Vec2, p: Vec3) -> Color {
+        self.deref().value(uv, p)
+    }
+}
+
+/// Alternates between two textures in a 3D grid of cubes of side `scale`, so it does not
+/// depend on the UV mapping.
+pub struct Checker<A: Texture, B: Texture> {
+    pub even: A,
+    pub odd: B,
+    pub scale: f32,
+}
+
+impl<A: Texture, B: Texture> Checker<A, B> {
+    pub fn new(even: A, odd: B, scale: f32) -> Self {
+        Self { even, odd, scale }
+    }
+}
+
+impl<A: Texture, B: Texture> Texture for Checker<A, B> {
+    fn value(&self, uv: Vec2, p: Vec3) -> Color {
+        let q = p / self.scale;
+        // The casts saturate far from the origin, where the sum must not overflow.
+        let sum = (q.x.floor() as i64)
+            .wrapping_add(q.y.floor() as i64)
+            .wrapping_add(q.z.floor() as i64);
+        if sum % 2 == 0 {
+            self.even.value(uv, p)
+        } else {
+            self.odd.value(uv, p)
+        }
+    }
+}
+
+/// How UV coordinates outside of `[0, 1]` are mapped onto an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Tiles the image.
+    Repeat,
+    /// Extends the border pixels.
+    Clamp,
+    /// Tiles the image, flipping every other copy so that there are no seams.
+    Mirror,
+}
+
+impl WrapMode {
+    pub const ALL: [WrapMode; 3] = [WrapMode::Repeat, WrapMode::Clamp, WrapMode::Mirror];
+
+    fn name(self) -> &'static str {
+        match self {
+            WrapMode::Repeat => "repeat",
+            WrapMode::Clamp => "clamp",
+            WrapMode::Mirror => "mirror",
+        }
+    }
+
+    /// Maps a texel coordinate onto `[0, size)`.
+    fn apply(self, i: i64, size: usize) -> usize {
+        let n = size as i64;
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(n),
+            WrapMode::Clamp => i.clamp(0, n - 1),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * n);
+                if i < n {
+                    i
+                } else {
+                    2 * n - 1 - i
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+impl fmt::Display for WrapMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for WrapMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|x| x.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown wrap mode `{}`, expected repeat, clamp or mirror",
+                    s
+                )
+            })
+    }
+}
+
+/// Bilinearly filtered image, with `(0, 0)` at its lower left corner.
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    /// Linear RGB, rows from top to bottom as in the file.
+    texels: Vec<Color>,
+    pub wrap: WrapMode,
+}
+
+impl ImageTexture {
+    /// Texture from 8-bit sRGB pixels, rows from top to bottom.
+    ///
+    /// Panics if `pixels` does not hold `width * height` pixels.
+    pub fn from_srgb8(width: usize, height: usize, pixels: &[[u8; 3]]) -> Self {
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+        assert!(width > 0 && height > 0, "empty image");
+
+        let mut lut = [0.0; 256];
+        for (i, x) in lut.iter_mut().enumerate() {
+            *x = srgb_decode(i as f32 / 255.0);
+        }
+
+        Self {
+            width,
+            height,
+            texels: pixels
+                .iter()
+                .map(|p| vec3(lut[p[0] as usize], lut[p[1] as usize], lut[p[2] as usize]))
+                .collect(),
+            wrap: WrapMode::Repeat,
+        }
+    }
+
+    /// Loads a PNG or JPEG file.
+    pub fn load(path: impl AsRef<Path>) -> image::ImageResult<Self> {
+        let img = image::open(path)?.into_rgb8();
+        let (w, h) = img.dimensions();
+        let pixels: Vec<[u8; 3]> = img.pixels().map(|p| p.0).collect();
+        Ok(Self::from_srgb8(w as usize, h as usize, &pixels))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn texel(&self, x: i64, y: i64) -> Color {
+        let x = self.wrap.apply(x, self.width);
+        let y = self.wrap.apply(y, self.height);
+        self.texels[y * self.width + x]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, uv: Vec2, _p: Vec3) -> Color {
+        // Texel centers are at half-integer coordinates.
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = (1.0 - uv.y) * self.height as f32 - 0.5;
+        if !(x.is_finite() && y.is_finite()) {
+            return Color::zero();
+        }
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = self.texel(x0, y0) * (1.0 - fx) + self.texel(x0 + 1, y0) * fx;
+        let bottom = self.texel(x0, y0 + 1) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
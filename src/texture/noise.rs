@@ -0,0 +1,246 @@
+//! Procedural textures built on Perlin and Worley noise.
+
+use crate::raytrace::Color;
+use crate::texture::Texture;
+use crate::utils::{random_unit_vector, splitmix64, SampleRng};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::fmt;
+use std::str::FromStr;
+use ultraviolet::{Vec2, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise, smooth and in `[-1, 1]`, with features of size 1.
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm: [Vec<u8>; 3],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = SampleRng::seed_from_u64(seed);
+        let gradients = (0..POINT_COUNT)
+            .map(|_| random_unit_vector(&mut rng))
+            .collect();
+        let mut perm = || {
+            let mut p: Vec<u8> = (0..POINT_COUNT).map(|i| i as u8).collect();
+            p.shuffle(&mut rng);
+            p
+        };
+        let perm = [perm(), perm(), perm()];
+        Self { gradients, perm }
+    }
+
+    fn gradient(&self, i: i32, j: i32, k: i32) -> Vec3 {
+        let idx = self.perm[0][(i & 255) as usize]
+            ^ self.perm[1][(j & 255) as usize]
+            ^ self.perm[2][(k & 255) as usize];
+        self.gradients[idx as usize]
+    }
+
+    pub fn noise(&self, p: Vec3) -> f32 {
+        let (fx, fy, fz) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (u, v, w) = (p.x - fx, p.y - fy, p.z - fz);
+        let (i, j, k) = (fx as i32, fy as i32, fz as i32);
+
+        // Hermite smoothing removes the grid artifacts of trilinear interpolation.
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        let mut acc = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let weight = Vec3::new(u - di as f32, v - dj as f32, w - dk as f32);
+                    let g = self.gradient(i + di, j + dj, k + dk);
+                    let (a, b, c) = (di as f32, dj as f32, dk as f32);
+                    acc += (a * uu + (1.0 - a) * (1.0 - uu))
+                        * (b * vv + (1.0 - b) * (1.0 - vv))
+                        * (c * ww + (1.0 - c) * (1.0 - ww))
+                        * g.dot(weight);
+                }
+            }
+        }
+        acc
+    }
+
+    /// Sum of `octaves` layers of noise, each twice as detailed and half as strong as the
+    /// previous one.
+    pub fn fbm(&self, p: Vec3, octaves: u32) -> f32 {
+        octave_sum(p, octaves, |p| self.noise(p))
+    }
+
+    /// Like [`fbm`](Perlin::fbm) on the absolute value of the noise, giving sharp creases.
+    pub fn turbulence(&self, p: Vec3, octaves: u32) -> f32 {
+        octave_sum(p, octaves, |p| self.noise(p).abs())
+    }
+}
+
+fn octave_sum(mut p: Vec3, octaves: u32, f: impl Fn(Vec3) -> f32) -> f32 {
+    let mut acc = 0.0;
+    let mut weight = 1.0;
+    for _ in 0..octaves.max(1) {
+        acc += weight * f(p);
+        weight *= 0.5;
+        p *= 2.0;
+    }
+    acc
+}
+
+/// Cellular noise: distance to the closest of randomly scattered feature points, one per unit
+/// cell.
+pub struct Worley {
+    seed: u64,
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    fn feature_point(&self, i: i32, j: i32, k: i32) -> Vec3 {
+        let mut h = splitmix64(self.seed ^ splitmix64((i as u32 as u64) << 32 | j as u32 as u64));
+        h = splitmix64(h ^ k as u32 as u64);
+        let unit = |bits: u64| (bits & 0x1F_FFFF) as f32 / (1u64 << 21) as f32;
+        Vec3::new(
+            i as f32 + unit(h),
+            j as f32 + unit(h >> 21),
+            k as f32 + unit(h >> 42),
+        )
+    }
+
+    /// Distance to the closest feature point, mostly within `[0, 1]`.
+    pub fn noise(&self, p: Vec3) -> f32 {
+        let (i, j, k) = (p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32);
+        let mut closest = f32::INFINITY;
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let d = (self.feature_point(i + di, j + dj, k + dk) - p).mag_sq();
+                    closest = closest.min(d);
+                }
+            }
+        }
+        closest.sqrt()
+    }
+
+    pub fn fbm(&self, p: Vec3, octaves: u32) -> f32 {
+        octave_sum(p, octaves, |p| self.noise(p))
+    }
+}
+
+/// How noise is turned into a blend factor between two colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Fractal Perlin noise.
+    Noise,
+    /// Fractal absolute Perlin noise, like billowing smoke.
+    Turbulence,
+    /// Sine stripes along `z` perturbed by turbulence.
+    Marble,
+    /// Concentric rings around the `y` axis perturbed by turbulence.
+    Wood,
+    /// Fractal Worley noise, like cells or stones.
+    Cellular,
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 5] = [
+        Pattern::Noise,
+        Pattern::Turbulence,
+        Pattern::Marble,
+        Pattern::Wood,
+        Pattern::Cellular,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Pattern::Noise => "noise",
+            Pattern::Turbulence => "turbulence",
+            Pattern::Marble => "marble",
+            Pattern::Wood => "wood",
+            Pattern::Cellular => "cellular",
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|x| x.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|x| x.name()).collect();
+                format!(
+                    "unknown noise pattern `{}`, expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Blends between the colors `low` and `high` following a noise [`Pattern`], `scale` being its
+/// frequency.
+pub struct NoiseTexture {
+    pub pattern: Pattern,
+    pub scale: f32,
+    pub octaves: u32,
+    pub low: Color,
+    pub high: Color,
+    perlin: Perlin,
+    worley: Worley,
+}
+
+impl NoiseTexture {
+    /// Black to white noise, with 7 octaves.
+    pub fn new(pattern: Pattern, scale: f32, seed: u64) -> Self {
+        Self {
+            pattern,
+            scale,
+            octaves: 7,
+            low: Color::zero(),
+            high: Color::one(),
+            perlin: Perlin::new(seed),
+            worley: Worley::new(seed),
+        }
+    }
+
+    /// The blend factor at `p`, in `[0, 1]`.
+    pub fn factor(&self, p: Vec3) -> f32 {
+        let q = p * self.scale;
+        let t = match self.pattern {
+            Pattern::Noise => 0.5 + 0.5 * self.perlin.fbm(q, self.octaves),
+            Pattern::Turbulence => self.perlin.turbulence(q, self.octaves),
+            // The scale only applies to the stripes and rings, not to their perturbation.
+            Pattern::Marble => {
+                0.5 + 0.5 * (q.z + 10.0 * self.perlin.turbulence(p, self.octaves)).sin()
+            }
+            Pattern::Wood => {
+                let r =
+                    (q.x * q.x + q.z * q.z).sqrt() + 2.0 * self.perlin.turbulence(p, self.octaves);
+                r.fract()
+            }
+            Pattern::Cellular => 0.5 * self.worley.fbm(q, self.octaves),
+        };
+        t.clamp(0.0, 1.0)
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _uv: Vec2, p: Vec3) -> Color {
+        let t = self.factor(p);
+        self.low * (1.0 - t) + self.high * t
+    }
+}
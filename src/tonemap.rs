@@ -0,0 +1,174 @@
+//! Conversion of linear radiance to displayable 8-bit sRGB.
+
+use crate::raytrace::{vec3, Color};
+use std::fmt;
+use std::str::FromStr;
+
+/// Tone mapping curve, compressing unbounded radiance into `[0, 1]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    /// No compression, everything above 1 is clipped.
+    Linear,
+    /// `L / (1 + L)` on luminance, never reaches white.
+    Reinhard,
+    /// Reinhard with a white point, luminance above it is clipped.
+    ExtendedReinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    ACES,
+    /// John Hable's Uncharted 2 filmic curve.
+    Hable,
+}
+
+impl Operator {
+    pub const ALL: [Operator; 5] = [
+        Operator::Linear,
+        Operator::Reinhard,
+        Operator::ExtendedReinhard,
+        Operator::ACES,
+        Operator::Hable,
+    ];
+
+    /// The next operator, wrapping around, to cycle through them in the viewer.
+    pub fn next(self) -> Operator {
+        let i = Self::ALL.iter().position(|&x| x == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Operator::Linear => "linear",
+            Operator::Reinhard => "reinhard",
+            Operator::ExtendedReinhard => "reinhard-extended",
+            Operator::ACES => "aces",
+            Operator::Hable => "hable",
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Operator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|x| x.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|x| x.name()).collect();
+                format!(
+                    "unknown tone mapping operator `{}`, expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+fn luminance(c: Color) -> f32 {
+    c.dot(vec3(0.2126, 0.7152, 0.0722))
+}
+
+/// Scales the color so that its luminance becomes `new_lum`, preserving the hue.
+fn with_luminance(c: Color, new_lum: f32) -> Color {
+    let lum = luminance(c);
+    if lum <= 0.0 {
+        return Color::zero();
+    }
+    c * (new_lum / lum)
+}
+
+fn aces(x: f32) -> f32 {
+    let x = x * 0.6;
+    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+}
+
+fn hable_partial(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+fn hable(x: f32) -> f32 {
+    const EXPOSURE_BIAS: f32 = 2.0;
+    const WHITE: f32 = 11.2;
+    hable_partial(x * EXPOSURE_BIAS) / hable_partial(WHITE)
+}
+
+/// The sRGB opto-electronic transfer function, for values in `[0, 1]`.
+pub fn srgb_encode(x: f32) -> f32 {
+    if x <= 0.003_130_8 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Inverse of [`srgb_encode`], converting sRGB values in `[0, 1]` back to linear.
+pub fn srgb_decode(x: f32) -> f32 {
+    if x <= 0.040_45 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ToneMapper {
+    pub operator: Operator,
+    /// Exposure compensation in stops, radiance is multiplied by `2^exposure`.
+    pub exposure: f32,
+    /// Luminance mapped to white by [`Operator::ExtendedReinhard`].
+    pub white: f32,
+}
+
+impl Default for ToneMapper {
+    fn default() -> Self {
+        Self {
+            operator: Operator::Linear,
+            exposure: 0.0,
+            white: 4.0,
+        }
+    }
+}
+
+impl ToneMapper {
+    /// Applies exposure and the tone curve, the result is still linear but within `[0, 1]`.
+    pub fn map(&self, c: Color) -> Color {
+        let c = c * 2f32.powf(self.exposure);
+        let mapped = match self.operator {
+            Operator::Linear => c,
+            Operator::Reinhard => {
+                let l = luminance(c);
+                with_luminance(c, l / (1.0 + l))
+            }
+            Operator::ExtendedReinhard => {
+                let l = luminance(c);
+                let w2 = self.white * self.white;
+                with_luminance(c, l * (1.0 + l / w2) / (1.0 + l))
+            }
+            Operator::ACES => vec3(aces(c.x), aces(c.y), aces(c.z)),
+            Operator::Hable => vec3(hable(c.x), hable(c.y), hable(c.z)),
+        };
+        mapped.clamped(Color::zero(), Color::one())
+    }
+
+    /// Tone maps and encodes to 8-bit sRGB.
+    pub fn to_srgb8(&self, c: Color) -> [u8; 3] {
+        let c = self.map(c);
+        [
+            (srgb_encode(c.x) * 255.0).round() as u8,
+            (srgb_encode(c.y) * 255.0).round() as u8,
+            (srgb_encode(c.z) * 255.0).round() as u8,
+        ]
+    }
+}
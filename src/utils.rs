@@ -1,22 +1,83 @@
+use crate::ray::Ray;
 use crate::raytrace::vec3;
+use rand::Rng;
 use ultraviolet::Vec3;
 
-pub fn random_unit_vector() -> Vec3 {
-    let a = rand::random::<f32>() * 2.0 * std::f32::consts::PI;
-    let z = rand::random::<f32>() * 2.0 - 1.0;
+/// Random number generator used for sampling. PCG is fully specified, so a given seed gives the
+/// same numbers on every platform.
+pub type SampleRng = rand_pcg::Pcg32;
+
+pub(crate) fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Generator for one sample of one pixel, independent of the order in which pixels are traced.
+pub fn sample_rng(seed: u64, x: u32, y: u32, sample: u32) -> SampleRng {
+    let pixel = (y as u64) << 32 | x as u64;
+    let state = splitmix64(splitmix64(seed ^ splitmix64(pixel)) ^ sample as u64);
+    SampleRng::new(state, 0x0A02_BDBF_7BB3_C0A7)
+}
+
+/// Generator seeded by a ray, for objects that need random numbers to be intersected such as
+/// participating media. Every path samples different rays so their numbers are independent, and
+/// tracing the same ray twice gives the same intersection.
+pub fn ray_rng(ray: &Ray) -> SampleRng {
+    let mut h = splitmix64(ray.time.to_bits() as u64);
+    for &x in &[ray.pos, ray.dir] {
+        h = splitmix64(h ^ ((x.x.to_bits() as u64) << 32 | x.y.to_bits() as u64));
+        h = splitmix64(h ^ x.z.to_bits() as u64);
+    }
+    SampleRng::new(h, 0x0A02_BDBF_7BB3_C0A7)
+}
+
+/// Uniformly distributed random point on the unit sphere.
+pub fn random_unit_vector(rng: &mut SampleRng) -> Vec3 {
+    let a = rng.gen::<f32>() * 2.0 * std::f32::consts::PI;
+    let z = rng.gen::<f32>() * 2.0 - 1.0;
     let r = (1.0 - z * z).sqrt();
-    return vec3(r * a.cos(), r * a.sin(), z);
+    vec3(r * a.cos(), r * a.sin(), z)
 }
 
-pub fn random_in_sphere() -> Vec3 {
+/// Random point inside the unit sphere.
+pub fn random_in_sphere(rng: &mut SampleRng) -> Vec3 {
     loop {
-        let p = Vec3::from(rand::random::<[f32; 3]>());
+        let p = Vec3::from(rng.gen::<[f32; 3]>()) * 2.0 - Vec3::one();
         if p.mag_sq() <= 1.0 {
             return p;
         }
     }
 }
 
+/// Random point inside the unit disk.
+pub fn random_in_disk(rng: &mut SampleRng) -> (f32, f32) {
+    loop {
+        let x = rng.gen::<f32>() * 2.0 - 1.0;
+        let y = rng.gen::<f32>() * 2.0 - 1.0;
+        if x * x + y * y <= 1.0 {
+            return (x, y);
+        }
+    }
+}
+
+/// Uniformly distributed random point inside the regular polygon with `n` vertices inscribed in
+/// the unit circle.
+pub fn random_in_polygon(rng: &mut SampleRng, n: u32) -> (f32, f32) {
+    let step = 2.0 * std::f32::consts::PI / n as f32;
+    let i = ((rng.gen::<f32>() * n as f32) as u32).min(n - 1);
+    let (a, b) = (i as f32 * step, (i + 1) as f32 * step);
+
+    // Point in the triangle formed by the center and one edge.
+    let (mut s, mut t) = (rng.gen::<f32>(), rng.gen::<f32>());
+    if s + t > 1.0 {
+        s = 1.0 - s;
+        t = 1.0 - t;
+    }
+    (s * a.cos() + t * b.cos(), s * a.sin() + t * b.sin())
+}
+
 pub fn v_min(a: Vec3, b: Vec3) -> Vec3 {
     Vec3 {
         x: a.x.min(b.x),
@@ -32,3 +93,15 @@ pub fn v_max(a: Vec3, b: Vec3) -> Vec3 {
         z: a.z.max(b.z),
     }
 }
+
+/// Two unit vectors forming an orthonormal basis with the unit vector `n`.
+pub fn orthonormal_basis(n: Vec3) -> (Vec3, Vec3) {
+    // Duff et al., "Building an Orthonormal Basis, Revisited".
+    let sign = 1.0f32.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    (
+        vec3(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+        vec3(b, sign + n.y * n.y * a, -n.y),
+    )
+}
@@ -0,0 +1,168 @@
+//! Voxel grids of density for heterogeneous participating media.
+
+use crate::aabb::AABB;
+use crate::texture::noise::Perlin;
+use rayon::prelude::*;
+use std::convert::TryInto;
+use std::io;
+use std::path::Path;
+use ultraviolet::Vec3;
+
+/// Densities sampled on a regular grid of voxels covering a box, and interpolated between
+/// their centers.
+pub struct DensityGrid {
+    res: [usize; 3],
+    data: Vec<f32>,
+    bbox: AABB,
+    max: f32,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl DensityGrid {
+    /// Grid of `res` voxels covering `bbox`, `data` lists their densities with `x` varying the
+    /// fastest, then `y`, then `z`. Negative densities are replaced by zero.
+    pub fn new(res: [usize; 3], mut data: Vec<f32>, bbox: AABB) -> Self {
+        assert!(res.iter().all(|&n| n > 0), "empty density grid");
+        assert_eq!(data.len(), res[0] * res[1] * res[2]);
+        for x in &mut data {
+            if x.is_nan() || *x < 0.0 {
+                *x = 0.0;
+            }
+        }
+        let max = data.iter().copied().fold(0.0, f32::max);
+        Self {
+            res,
+            data,
+            bbox,
+            max,
+        }
+    }
+
+    /// Samples `f` at the center of every voxel.
+    pub fn from_fn(res: [usize; 3], bbox: AABB, f: impl Fn(Vec3) -> f32 + Sync) -> Self {
+        let size = bbox.hi - bbox.lo;
+        let data = (0..res[0] * res[1] * res[2])
+            .into_par_iter()
+            .map(|i| {
+                let (x, y, z) = (i % res[0], i / res[0] % res[1], i / (res[0] * res[1]));
+                let rel = Vec3::new(
+                    (x as f32 + 0.5) / res[0] as f32,
+                    (y as f32 + 0.5) / res[1] as f32,
+                    (z as f32 + 0.5) / res[2] as f32,
+                );
+                f(bbox.lo + rel * size)
+            })
+            .collect();
+        Self::new(res, data, bbox)
+    }
+
+    /// Procedural cloud filling `bbox`: a ball of density up to 1 whose edges are eroded by
+    /// Perlin noise of frequency `scale`.
+    pub fn cloud(res: [usize; 3], bbox: AABB, scale: f32, seed: u64) -> Self {
+        let perlin = Perlin::new(seed);
+        let center = bbox.center();
+        let half = (bbox.hi - bbox.lo) * 0.5;
+        Self::from_fn(res, bbox, |p| {
+            let r = ((p - center) / half).mag();
+            (2.0 * (1.0 - r) + perlin.fbm(p * scale, 5)).min(1.0)
+        })
+    }
+
+    /// Parses a grid in the VOL format of Mitsuba: single channel, `float32` or `uint8`
+    /// encoded, in which case densities go from 0 to 1.
+    pub fn parse(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < 48 || &bytes[..3] != b"VOL" || bytes[3] != 3 {
+            return Err(invalid("not a version 3 VOL file".to_owned()));
+        }
+        let int = |i: usize| i32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let float = |i: usize| f32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+
+        let encoding = int(4);
+        let dims = [int(8), int(12), int(16)];
+        let channels = int(20);
+        if channels != 1 {
+            return Err(invalid(format!(
+                "density grids have 1 channel, got {}",
+                channels
+            )));
+        }
+        if dims.iter().any(|&n| n <= 0) {
+            return Err(invalid(format!("invalid resolution {:?}", dims)));
+        }
+        let res = [dims[0] as usize, dims[1] as usize, dims[2] as usize];
+        let lo = [float(24), float(28), float(32)];
+        let hi = [float(36), float(40), float(44)];
+        if !(0..3).all(|a| lo[a] < hi[a]) {
+            return Err(invalid(format!("empty bounding box {:?} to {:?}", lo, hi)));
+        }
+        let bbox = AABB::new(Vec3::from(lo), Vec3::from(hi));
+
+        let too_large = || invalid(format!("resolution too large {:?}", dims));
+        let count = res[0]
+            .checked_mul(res[1])
+            .and_then(|n| n.checked_mul(res[2]))
+            .ok_or_else(too_large)?;
+        let size = count.checked_mul(4).ok_or_else(too_large)?;
+        let body = &bytes[48..];
+        let data = match encoding {
+            1 if body.len() >= size => (0..count).map(|i| float(48 + 4 * i)).collect(),
+            3 if body.len() >= count => body[..count].iter().map(|&x| x as f32 / 255.0).collect(),
+            1 | 3 => return Err(invalid("truncated VOL file".to_owned())),
+            _ => return Err(invalid(format!("unsupported VOL encoding {}", encoding))),
+        };
+        Ok(Self::new(res, data, bbox))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.res
+    }
+
+    pub fn bbox(&self) -> AABB {
+        self.bbox
+    }
+
+    /// Largest density in the grid, which bounds [`density`](DensityGrid::density).
+    pub fn max_density(&self) -> f32 {
+        self.max
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.data[(z * self.res[1] + y) * self.res[0] + x]
+    }
+
+    /// Trilinear interpolation of the density at `p`, zero outside of the box.
+    pub fn density(&self, p: Vec3) -> f32 {
+        let rel = (p - self.bbox.lo) / (self.bbox.hi - self.bbox.lo);
+        if !(0.0..=1.0).contains(&rel.x)
+            || !(0.0..=1.0).contains(&rel.y)
+            || !(0.0..=1.0).contains(&rel.z)
+        {
+            return 0.0;
+        }
+
+        // Voxel centers are at half-integer coordinates.
+        let mut idx = [0; 2 * 3];
+        let mut frac = [0.0; 3];
+        for a in 0..3 {
+            let g = (rel[a] * self.res[a] as f32 - 0.5).max(0.0);
+            let i = (g as usize).min(self.res[a] - 1);
+            idx[2 * a] = i;
+            idx[2 * a + 1] = (i + 1).min(self.res[a] - 1);
+            frac[a] = (g - i as f32).min(1.0);
+        }
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let plane = |z: usize| {
+            let row = |y: usize| lerp(self.voxel(idx[0], y, z), self.voxel(idx[1], y, z), frac[0]);
+            lerp(row(idx[2]), row(idx[3]), frac[1])
+        };
+        lerp(plane(idx[4]), plane(idx[5]), frac[2])
+    }
+}
@@ -0,0 +1,34 @@
+use raytracing::hittable::Hittable;
+use raytracing::image_gen::ImageProvider;
+use raytracing::raytrace::RayTracer;
+use raytracing::scenes;
+
+/// Renders a few samples in a pool of `threads` threads and returns the bits of the image.
+fn render(threads: usize, load: impl Fn() -> RayTracer<Box<dyn Hittable>> + Sync) -> Vec<u32> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        let mut tracer = load();
+        tracer.seed = 7;
+        let mut provider = ImageProvider::new();
+        for _ in 0..4 {
+            provider.get_next(&mut tracer, 32, 24);
+        }
+        provider
+            .linear()
+            .iter()
+            .flat_map(|c| [c.x.to_bits(), c.y.to_bits(), c.z.to_bits()])
+            .collect()
+    })
+}
+
+#[test]
+fn seeded_renders_do_not_depend_on_threads() {
+    let random = || RayTracer::new(Box::new(scenes::random_scene(3)) as Box<dyn Hittable>);
+    assert_eq!(render(1, random), render(4, random));
+
+    let smoke = || scenes::file::load("scenes/cornell_smoke.toml").unwrap();
+    assert_eq!(render(1, smoke), render(4, smoke));
+}